@@ -4,6 +4,8 @@ export HEDRON="hedron/build/src/hypervisor.elf32"
 # "debug" or "release"
 export RELEASE=release
 export COMPILER_TARGET="x86_64-unknown-none"
+# Target of the host-side unit tests
+export TEST_TARGET="x86_64-unknown-linux-gnu"
 # The roottask to bootstrap
 export ROOTTASK="roottask/target/${COMPILER_TARGET}/${RELEASE}/hmr_stripped"
 
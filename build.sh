@@ -13,6 +13,7 @@ source .config.sh
 
 function fn_main() {
     fn_build_hedron
+    fn_test_libhedron
     fn_build_rust
     fn_build_rust_strip
 }
@@ -30,11 +31,23 @@ function fn_build_hedron() {
     cd ..
 }
 
+# The ABI bindings are a regular library crate. Its unit tests run on the host.
+function fn_test_libhedron() {
+    cd libhedron || exit 1
+    cargo fmt
+    cargo clippy --target "${TEST_TARGET}"
+    cargo test --target "${TEST_TARGET}"
+    cargo test --target "${TEST_TARGET}" --features simulator
+    cd ..
+}
+
 function fn_build_rust() {
     cd roottask || exit 1
     cargo check
     cargo fmt
     cargo clippy
+    # unit tests run on the host against the simulator of libhedron
+    cargo test --target "${TEST_TARGET}"
     cargo build --release
     cd ..
 }
@@ -0,0 +1,79 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the `assign_gsi` syscall.
+
+use crate::capability::CapSel;
+use crate::error::HedronError;
+use crate::hip::Hip;
+use crate::syscall::{encode_arg1, generic_syscall, SyscallNum};
+use crate::NUM_CAP_SEL;
+
+/// The values that a driver must program into the MSI capability of a device, so that
+/// the interrupts of the device arrive at the kernel. Only valid for MSIs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MsiInfo {
+    /// Value for the MSI address register.
+    pub address: u64,
+    /// Value for the MSI data register.
+    pub data: u64,
+}
+
+/// Returns the capability selector of the interrupt semaphore of a global system interrupt
+/// (GSI) in the capability space of the hypervisor PD. By convention, the kernel places these
+/// semaphores right after the per-CPU semaphores, i.e., at `NUM_CPU + gsi`, where `NUM_CPU`
+/// is [`Hip::num_cpu_slots`] and not the number of enabled CPUs. The roottask can obtain them
+/// via `pd_ctrl_delegate` with the hypervisor as source.
+pub fn gsi_sm_kernel_sel(hip: &Hip, gsi: u64) -> CapSel {
+    assert!(gsi < hip.num_gsi_sel(), "GSI doesn't exist!");
+    hip.num_cpu_slots() + gsi
+}
+
+/// System call `assign_gsi` routes a global system interrupt (GSI) to a CPU. The GSI is
+/// identified by its interrupt semaphore. Each interrupt performs an `UP` operation on the
+/// semaphore. Hence, a driver EC can wait for interrupts with a `DOWN` operation.
+///
+/// # Parameters
+/// - `sm_sel` Capability selector of the interrupt semaphore of the GSI, see
+///   [`gsi_sm_kernel_sel`].
+/// - `dev_cfg_addr` For MSIs, the virtual address in the address space of the caller where the
+///   PCI configuration space (MMCONFIG page) of the device is mapped. Zero for IOAPIC pins.
+/// - `cpu` The CPU that receives the interrupt.
+///
+/// Returns the [`MsiInfo`] that the driver must program into the device, if the GSI is an MSI.
+pub fn assign_gsi(sm_sel: CapSel, dev_cfg_addr: u64, cpu: u16) -> Result<MsiInfo, HedronError> {
+    assert!(
+        sm_sel < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+
+    let arg1 = encode_arg1(SyscallNum::AssignGsi, 0, sm_sel);
+    let arg2 = dev_cfg_addr;
+    let arg3 = cpu as u64;
+
+    unsafe {
+        generic_syscall(arg1, arg2, arg3, 0, 0)
+            .map(|(address, data)| MsiInfo { address, data })
+            .map_err(|e| e.0)
+    }
+}
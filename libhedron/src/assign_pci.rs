@@ -0,0 +1,56 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the `assign_pci` syscall.
+
+use crate::capability::CapSel;
+use crate::error::HedronError;
+use crate::syscall::{encode_arg1, generic_syscall, SyscallNum};
+use crate::NUM_CAP_SEL;
+
+/// System call `assign_pci` assigns a PCI device to the device page table (DMA space) of a
+/// protection domain. Afterwards, DMA of the device is translated by the IOMMU according to
+/// the memory delegations with the `in_device_pt` flag into that PD.
+///
+/// # Parameters
+/// - `pd_sel` Capability selector of the PD that gets the device.
+/// - `dev_cfg_addr` Virtual address in the address space of the caller where the PCI
+///   configuration space (MMCONFIG page) of the device is mapped. It identifies the device.
+/// - `hint` The requester ID (bus/device/function) for DMA, if it differs from the one of the
+///   device, e.g., for devices behind a PCIe-to-PCI bridge. Otherwise, zero.
+pub fn assign_pci(pd_sel: CapSel, dev_cfg_addr: u64, hint: u64) -> Result<(), HedronError> {
+    assert!(
+        pd_sel < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+
+    let arg1 = encode_arg1(SyscallNum::AssignPci, 0, pd_sel);
+    let arg2 = dev_cfg_addr;
+    let arg3 = hint;
+
+    unsafe {
+        generic_syscall(arg1, arg2, arg3, 0, 0)
+            .map(|_x| ())
+            .map_err(|e| e.0)
+    }
+}
@@ -37,6 +37,7 @@ SOFTWARE.
 //! This module seems a bit overkill and over-engineered, but makes it unable to create invalid
 //! Crds. Furthermore, it puts all knowledge about Crds from Hedron into Code.
 
+use crate::error::HedronError;
 use core::fmt::{Debug, Formatter};
 use core::marker::PhantomData;
 
@@ -92,15 +93,28 @@ impl CrdKind {
     pub fn val(self) -> u8 {
         self as u8
     }
+
+    /// Decodes the two lowest bits, which always form a valid kind.
+    pub(crate) fn from_lowest_bits(val: u8) -> Self {
+        match val & 0b11 {
+            0 => Self::Null,
+            1 => Self::Memory,
+            2 => Self::PortIo,
+            _ => Self::Object,
+        }
+    }
 }
 
-impl From<u8> for CrdKind {
+impl TryFrom<u8> for CrdKind {
+    type Error = HedronError;
+
     /// Creates a CrdKind from an unsigned integer value.
-    /// Panics if value is invalid.
-    fn from(val: u8) -> Self {
-        let max = CrdKind::Object as u8;
-        assert!(val <= max);
-        unsafe { core::mem::transmute(val) }
+    fn try_from(val: u8) -> Result<Self, Self::Error> {
+        if val > CrdKind::Object as u8 {
+            Err(HedronError::InvalidCrdKind(val))
+        } else {
+            Ok(Self::from_lowest_bits(val))
+        }
     }
 }
 
@@ -281,7 +295,7 @@ impl<Permissions, Specialization, ObjectSpecialization>
 
     /// Returns the [`CrdKind`] of this [`Crd`].
     pub fn kind(self) -> CrdKind {
-        CrdKind::from((self.val & Self::KIND_BITMASK) as u8)
+        CrdKind::from_lowest_bits((self.val & Self::KIND_BITMASK) as u8)
     }
 
     /// Returns the order of this [`Crd`]. `2^order` defines the range.
@@ -322,9 +336,10 @@ impl<Permissions, Specialization, ObjectSpecialization>
 where
     Permissions: CrdPermissions,
 {
-    /// Returns the [`CrdPermissions`]-type of this [`Crd`]. Depends on the context.
-    pub fn permissions(self) -> Permissions {
-        Permissions::from(self.gen_permissions())
+    /// Returns the [`CrdPermissions`]-type of this [`Crd`]. Depends on the context. Fails,
+    /// if the raw value has permission bits that are not defined for the type.
+    pub fn permissions(self) -> Result<Permissions, HedronError> {
+        Permissions::try_from(self.gen_permissions())
     }
 }
 
@@ -564,7 +579,7 @@ impl Debug for CrdObjPT {
 }
 
 /// Shared trait for all permission implementations.
-pub trait CrdPermissions: From<u8> + Into<u8> + Default {
+pub trait CrdPermissions: TryFrom<u8, Error = HedronError> + Into<u8> + Default {
     /// Returns a raw unsigned integer with the permission bits.
     fn val(self) -> u8 {
         self.into()
@@ -679,9 +694,11 @@ macro_rules! impl_permission_traits {
             }
         }
 
-        impl From<u8> for $name {
-            fn from(val: u8) -> Self {
-                Self::from_bits(val).unwrap()
+        impl TryFrom<u8> for $name {
+            type Error = HedronError;
+
+            fn try_from(val: u8) -> Result<Self, Self::Error> {
+                Self::from_bits(val).ok_or(HedronError::InvalidPermissions(val))
             }
         }
 
@@ -693,7 +710,7 @@ macro_rules! impl_permission_traits {
 
         impl Default for $name {
             fn default() -> Self {
-                Self::from(0)
+                Self::empty()
             }
         }
 
@@ -709,3 +726,28 @@ impl_permission_traits!(PDCapPermissions);
 impl_permission_traits!(ECCapPermissions);
 impl_permission_traits!(SCCapPermissions);
 impl_permission_traits!(SMCapPermissions);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crd_kind_try_from() {
+        assert_eq!(CrdKind::try_from(3), Ok(CrdKind::Object));
+        assert_eq!(CrdKind::try_from(4), Err(HedronError::InvalidCrdKind(4)));
+    }
+
+    #[test]
+    fn test_permissions_try_from() {
+        assert_eq!(
+            SMCapPermissions::try_from(0b11),
+            Ok(SMCapPermissions::UP | SMCapPermissions::DOWN)
+        );
+        assert_eq!(
+            SMCapPermissions::try_from(0b100),
+            Err(HedronError::InvalidPermissions(0b100))
+        );
+        let crd = CrdMem::new(0x1000, 2, MemCapPermissions::READ);
+        assert_eq!(crd.permissions(), Ok(MemCapPermissions::READ));
+    }
+}
@@ -0,0 +1,100 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the `create_ec` syscall.
+
+use crate::capability::CapSel;
+use crate::error::HedronError;
+use crate::syscall::{encode_arg1, generic_syscall, SyscallNum};
+use crate::NUM_CAP_SEL;
+
+/// The kind of an execution context (EC).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum EcKind {
+    /// A local EC has no scheduling context on its own. It only executes, when a portal
+    /// bound to it is called. Local ECs are used to handle IPC, exceptions, and VM exits.
+    Local = 0,
+    /// A global EC executes on its own, as soon as a scheduling context is bound to it.
+    /// It starts with a startup exception.
+    Global = 1,
+}
+
+impl EcKind {
+    /// Returns the raw unsigned integer value.
+    pub fn val(self) -> u8 {
+        self as u8
+    }
+}
+
+/// System call `create_ec` creates a new execution context (EC), i.e., a thread.
+///
+/// # Parameters
+/// - `dest_sel` The free capability selector in the capability space of the caller that will
+///   refer to the new EC.
+/// - `owner_pd` Capability selector of the PD the EC belongs to. The capability needs the
+///   [`crate::capability::PDCapPermissions::CREATE_KOBJECTS`] permission.
+/// - `kind` See [`EcKind`].
+/// - `cpu` The number of the CPU the EC is bound to. ECs can't migrate.
+/// - `utcb_addr` Page-aligned virtual address of the UTCB in the address space of `owner_pd`.
+/// - `stack_ptr` The initial stack pointer. For local ECs, this is the stack pointer on every
+///   portal invocation.
+/// - `evt_base` The first capability selector of the portals in the capability space of
+///   `owner_pd` that handle the exceptions of the EC.
+pub fn create_ec(
+    dest_sel: CapSel,
+    owner_pd: CapSel,
+    kind: EcKind,
+    cpu: u16,
+    utcb_addr: u64,
+    stack_ptr: u64,
+    evt_base: CapSel,
+) -> Result<(), HedronError> {
+    assert!(
+        dest_sel < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+    assert!(
+        owner_pd < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+    assert!(
+        evt_base < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+    assert_eq!(utcb_addr & 0xfff, 0, "the UTCB must be page-aligned!");
+    const CPU_BITMASK: u64 = 0xfff;
+    assert!(cpu as u64 <= CPU_BITMASK, "maximum CPU number exceeded!");
+
+    let arg1 = encode_arg1(SyscallNum::CreateEc, kind.val(), dest_sel);
+    let arg2 = owner_pd;
+    let arg3 = utcb_addr | (cpu as u64 & CPU_BITMASK);
+    let arg4 = stack_ptr;
+    let arg5 = evt_base;
+
+    unsafe {
+        generic_syscall(arg1, arg2, arg3, arg4, arg5)
+            .map(|_x| ())
+            .map_err(|e| e.0)
+    }
+}
@@ -0,0 +1,63 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the `create_pd` syscall.
+
+use crate::capability::{CapSel, Crd};
+use crate::error::HedronError;
+use crate::syscall::{encode_arg1, generic_syscall, SyscallNum};
+use crate::NUM_CAP_SEL;
+
+/// System call `create_pd` creates a new protection domain (PD).
+///
+/// # Parameters
+/// - `dest_sel` The free capability selector in the capability space of the caller that will
+///   refer to the new PD.
+/// - `parent_pd` Capability selector of the PD that will own the new PD. The capability needs
+///   the [`crate::capability::PDCapPermissions::CREATE_KOBJECTS`] permission.
+/// - `crd` Object capabilities that are delegated from the parent PD to the new PD right away.
+///   Use [`crate::capability::CrdNull`] to not delegate anything.
+pub fn create_pd<Perm, Spec, ObjSpec>(
+    dest_sel: CapSel,
+    parent_pd: CapSel,
+    crd: Crd<Perm, Spec, ObjSpec>,
+) -> Result<(), HedronError> {
+    assert!(
+        dest_sel < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+    assert!(
+        parent_pd < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+
+    let arg1 = encode_arg1(SyscallNum::CreatePd, 0, dest_sel);
+    let arg2 = parent_pd;
+    let arg3 = crd.val();
+
+    unsafe {
+        generic_syscall(arg1, arg2, arg3, 0, 0)
+            .map(|_x| ())
+            .map_err(|e| e.0)
+    }
+}
@@ -0,0 +1,75 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the `create_pt` syscall.
+
+use crate::capability::CapSel;
+use crate::error::HedronError;
+use crate::mtd::Mtd;
+use crate::syscall::{encode_arg1, generic_syscall, SyscallNum};
+use crate::NUM_CAP_SEL;
+
+/// System call `create_pt` creates a new portal (PT). A portal is the entry point
+/// into a local EC. It is called via IPC or by the kernel for exceptions.
+///
+/// # Parameters
+/// - `dest_sel` The free capability selector in the capability space of the caller that will
+///   refer to the new PT.
+/// - `owner_pd` Capability selector of the PD the PT belongs to. The capability needs the
+///   [`crate::capability::PDCapPermissions::CREATE_KOBJECTS`] permission.
+/// - `ec_sel` Capability selector of the local EC that handles calls to the portal. The
+///   capability needs the [`crate::capability::ECCapPermissions::CREATE_PT`] permission.
+/// - `mtd` See [`Mtd`].
+/// - `entry_ip` The instruction pointer where the local EC starts for each portal invocation.
+pub fn create_pt(
+    dest_sel: CapSel,
+    owner_pd: CapSel,
+    ec_sel: CapSel,
+    mtd: Mtd,
+    entry_ip: u64,
+) -> Result<(), HedronError> {
+    assert!(
+        dest_sel < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+    assert!(
+        owner_pd < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+    assert!(
+        ec_sel < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+
+    let arg1 = encode_arg1(SyscallNum::CreatePt, 0, dest_sel);
+    let arg2 = owner_pd;
+    let arg3 = ec_sel;
+    let arg4 = mtd.bits();
+    let arg5 = entry_ip;
+
+    unsafe {
+        generic_syscall(arg1, arg2, arg3, arg4, arg5)
+            .map(|_x| ())
+            .map_err(|e| e.0)
+    }
+}
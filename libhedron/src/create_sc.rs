@@ -0,0 +1,121 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the `create_sc` syscall.
+
+use crate::capability::CapSel;
+use crate::error::HedronError;
+use crate::syscall::{encode_arg1, generic_syscall, SyscallNum};
+use crate::NUM_CAP_SEL;
+
+/// Quantum Priority Descriptor (QPD). Describes the priority and the
+/// time slice of a scheduling context.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Qpd(u64);
+
+impl Qpd {
+    /// Highest priority supported by the kernel.
+    pub const MAX_PRIORITY: u8 = 127;
+    /// Default time slice in microseconds.
+    pub const DEFAULT_QUANTUM: u64 = 10000;
+
+    const PRIORITY_BITMASK: u64 = 0xff;
+    const QUANTUM_BITSHIFT: u64 = 12;
+
+    /// Creates a new QPD.
+    ///
+    /// # Parameters
+    /// - `priority` Priority of the SC in range `1..=127`. Higher values take precedence.
+    /// - `quantum` Time slice in microseconds. Must not be zero.
+    pub fn new(priority: u8, quantum: u64) -> Self {
+        assert!(
+            priority > 0 && priority <= Self::MAX_PRIORITY,
+            "invalid priority!"
+        );
+        assert!(quantum > 0, "quantum must not be zero!");
+        Self((priority as u64 & Self::PRIORITY_BITMASK) | (quantum << Self::QUANTUM_BITSHIFT))
+    }
+
+    pub fn val(self) -> u64 {
+        self.0
+    }
+
+    /// Returns the priority.
+    pub fn priority(self) -> u8 {
+        (self.0 & Self::PRIORITY_BITMASK) as u8
+    }
+
+    /// Returns the time slice in microseconds.
+    pub fn quantum(self) -> u64 {
+        self.0 >> Self::QUANTUM_BITSHIFT
+    }
+}
+
+impl Default for Qpd {
+    /// Lowest priority with the default time slice.
+    fn default() -> Self {
+        Self::new(1, Self::DEFAULT_QUANTUM)
+    }
+}
+
+/// System call `create_sc` creates a new scheduling context (SC) and binds it to a
+/// global EC. As soon as the SC exists, the EC is scheduled.
+///
+/// # Parameters
+/// - `dest_sel` The free capability selector in the capability space of the caller that will
+///   refer to the new SC.
+/// - `owner_pd` Capability selector of the PD the SC belongs to. The capability needs the
+///   [`crate::capability::PDCapPermissions::CREATE_KOBJECTS`] permission.
+/// - `ec_sel` Capability selector of the global EC. The capability needs the
+///   [`crate::capability::ECCapPermissions::CREATE_SC`] permission.
+/// - `qpd` See [`Qpd`].
+pub fn create_sc(
+    dest_sel: CapSel,
+    owner_pd: CapSel,
+    ec_sel: CapSel,
+    qpd: Qpd,
+) -> Result<(), HedronError> {
+    assert!(
+        dest_sel < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+    assert!(
+        owner_pd < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+    assert!(
+        ec_sel < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+
+    let arg1 = encode_arg1(SyscallNum::CreateSc, 0, dest_sel);
+    let arg2 = owner_pd;
+    let arg3 = ec_sel;
+    let arg4 = qpd.val();
+
+    unsafe {
+        generic_syscall(arg1, arg2, arg3, arg4, 0)
+            .map(|_x| ())
+            .map_err(|e| e.0)
+    }
+}
@@ -0,0 +1,62 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the `create_sm` syscall.
+
+use crate::capability::CapSel;
+use crate::error::HedronError;
+use crate::syscall::{encode_arg1, generic_syscall, SyscallNum};
+use crate::NUM_CAP_SEL;
+
+/// System call `create_sm` creates a new semaphore (SM).
+///
+/// # Parameters
+/// - `dest_sel` The free capability selector in the capability space of the caller that will
+///   refer to the new SM.
+/// - `owner_pd` Capability selector of the PD the SM belongs to. The capability needs the
+///   [`crate::capability::PDCapPermissions::CREATE_KOBJECTS`] permission.
+/// - `initial_count` The initial counter value of the semaphore.
+pub fn create_sm(
+    dest_sel: CapSel,
+    owner_pd: CapSel,
+    initial_count: u64,
+) -> Result<(), HedronError> {
+    assert!(
+        dest_sel < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+    assert!(
+        owner_pd < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+
+    let arg1 = encode_arg1(SyscallNum::CreateSm, 0, dest_sel);
+    let arg2 = owner_pd;
+    let arg3 = initial_count;
+
+    unsafe {
+        generic_syscall(arg1, arg2, arg3, 0, 0)
+            .map(|_x| ())
+            .map_err(|e| e.0)
+    }
+}
@@ -0,0 +1,214 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Helpers that delegate arbitrary ranges of I/O ports and memory pages with
+//! PD_CTRL_DELEGATE.
+//!
+//! A [`Crd`] always describes a naturally aligned power-of-two range. These helpers split an
+//! arbitrary range into the minimal number of such chunks and issue one delegation per chunk.
+//!
+//! [`Crd`]: crate::capability::Crd
+
+use crate::capability::{CapSel, CrdMem, CrdPortIO, MemCapPermissions, MAX_CRD_ORDER};
+use crate::error::HedronError;
+use crate::pd_ctrl::{pd_ctrl_delegate, DelegateFlags};
+use crate::{PAGE_SIZE, ROOTTASK_CAPSEL};
+use core::ops::RangeInclusive;
+
+/// Error of a range delegation. The chunks before the failed one stay delegated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RangeDelegationError {
+    /// Number of ports or pages at the beginning of the range that were delegated.
+    pub delegated: u64,
+    /// Error of the delegation that failed.
+    pub error: HedronError,
+}
+
+/// One naturally aligned power-of-two chunk of a range. See [`aligned_chunks`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AlignedChunk {
+    /// Base in the source range.
+    pub src: u64,
+    /// Base in the destination range.
+    pub dest: u64,
+    /// The chunk covers `2^order` ports or pages.
+    pub order: u8,
+}
+
+impl AlignedChunk {
+    /// Returns the number of ports or pages of the chunk.
+    pub fn count(self) -> u64 {
+        1 << self.order
+    }
+}
+
+/// Splits the range of `count` units that starts at `src` in the source space and at `dest`
+/// in the destination space into the minimal number of chunks that are naturally aligned in
+/// both spaces. The chunks are returned in ascending order.
+pub fn aligned_chunks(src: u64, dest: u64, count: u64) -> impl Iterator<Item = AlignedChunk> {
+    let mut offset = 0;
+    core::iter::from_fn(move || {
+        let remaining = count - offset;
+        if remaining == 0 {
+            return None;
+        }
+        let src = src + offset;
+        let dest = dest + offset;
+        // the order is limited by the alignment of both bases and by the remaining length
+        let align_order = (src | dest).trailing_zeros();
+        let len_order = 63 - remaining.leading_zeros();
+        let order = align_order.min(len_order).min(MAX_CRD_ORDER as u32) as u8;
+        let chunk = AlignedChunk { src, dest, order };
+        offset += chunk.count();
+        Some(chunk)
+    })
+}
+
+/// Delegates the I/O ports of the range from the roottask (or the hypervisor, depending on
+/// `flags`) to the same ports in the I/O space of `dest_pd`.
+///
+/// # Example
+/// The roottask obtains the eight ports of COM1 from the hypervisor:
+/// ```ignore
+/// delegate_ports(0x3f8..=0x3ff, ROOTTASK_CAPSEL, DelegateFlags::builder().hypervisor(true).build())
+/// ```
+pub fn delegate_ports(
+    ports: RangeInclusive<u16>,
+    dest_pd: CapSel,
+    flags: DelegateFlags,
+) -> Result<(), RangeDelegationError> {
+    let first = *ports.start() as u64;
+    let count = (*ports.end() as u64 + 1).saturating_sub(first);
+    delegate_chunks(first, first, count, |chunk| {
+        pd_ctrl_delegate(
+            ROOTTASK_CAPSEL,
+            dest_pd,
+            CrdPortIO::new(chunk.src as u16, chunk.order),
+            CrdPortIO::new(chunk.dest as u16, chunk.order),
+            flags,
+        )
+    })
+}
+
+/// Delegates `page_count` pages of memory with the given permissions from the virtual page
+/// `src_page` of `src_pd` to the virtual page `dest_page` of `dest_pd`.
+pub fn delegate_mem_range(
+    src_pd: CapSel,
+    dest_pd: CapSel,
+    src_page: u64,
+    dest_page: u64,
+    page_count: u64,
+    permissions: MemCapPermissions,
+    flags: DelegateFlags,
+) -> Result<(), RangeDelegationError> {
+    delegate_chunks(src_page, dest_page, page_count, |chunk| {
+        pd_ctrl_delegate(
+            src_pd,
+            dest_pd,
+            CrdMem::new(chunk.src, chunk.order, permissions),
+            CrdMem::new(chunk.dest, chunk.order, permissions),
+            flags,
+        )
+    })
+}
+
+/// Maps `len` bytes of physical memory at `phys` to the virtual address `virt` of the
+/// roottask. `flags` must have the hypervisor flag set, because only then the source is the
+/// identity-mapped physical memory. Both addresses must be page-aligned; the length is
+/// rounded up to full pages.
+pub fn map_phys_range(
+    phys: u64,
+    virt: u64,
+    len: u64,
+    permissions: MemCapPermissions,
+    flags: DelegateFlags,
+) -> Result<(), RangeDelegationError> {
+    assert_eq!(phys % PAGE_SIZE, 0, "physical address must be page-aligned");
+    assert_eq!(virt % PAGE_SIZE, 0, "virtual address must be page-aligned");
+    delegate_mem_range(
+        ROOTTASK_CAPSEL,
+        ROOTTASK_CAPSEL,
+        phys / PAGE_SIZE,
+        virt / PAGE_SIZE,
+        (len + PAGE_SIZE - 1) / PAGE_SIZE,
+        permissions,
+        flags,
+    )
+}
+
+/// Issues one delegation per chunk of the range and stops at the first failure.
+fn delegate_chunks(
+    src: u64,
+    dest: u64,
+    count: u64,
+    mut delegate: impl FnMut(AlignedChunk) -> Result<(), HedronError>,
+) -> Result<(), RangeDelegationError> {
+    let mut delegated = 0;
+    for chunk in aligned_chunks(src, dest, count) {
+        delegate(chunk).map_err(|error| RangeDelegationError { delegated, error })?;
+        delegated += chunk.count();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(src: u64, dest: u64, count: u64) -> impl Iterator<Item = (u64, u64, u8)> {
+        aligned_chunks(src, dest, count).map(|c| (c.src, c.dest, c.order))
+    }
+
+    #[test]
+    fn test_aligned_chunks_single_chunk() {
+        // the eight registers of COM1
+        assert!(chunks(0x3f8, 0x3f8, 8).eq([(0x3f8, 0x3f8, 3)]));
+        // QEMUs debugcon port
+        assert!(chunks(0xe9, 0xe9, 1).eq([(0xe9, 0xe9, 0)]));
+    }
+
+    #[test]
+    fn test_aligned_chunks_unaligned_range() {
+        assert!(chunks(3, 3, 10).eq([(3, 3, 0), (4, 4, 2), (8, 8, 2), (12, 12, 0)]));
+    }
+
+    #[test]
+    fn test_aligned_chunks_different_alignment() {
+        // the destination is only aligned to 2 units
+        assert!(chunks(0, 2, 4).eq([(0, 2, 1), (2, 4, 1)]));
+    }
+
+    #[test]
+    fn test_aligned_chunks_cover_range() {
+        for (src, dest, count) in [(0, 0, 0), (1, 7, 1000), (0x1000, 0x20000, 0x12345)] {
+            let mut next = (src, dest);
+            for chunk in aligned_chunks(src, dest, count) {
+                assert_eq!((chunk.src, chunk.dest), next);
+                assert_eq!(chunk.src % chunk.count(), 0);
+                assert_eq!(chunk.dest % chunk.count(), 0);
+                next = (chunk.src + chunk.count(), chunk.dest + chunk.count());
+            }
+            assert_eq!(next, (src + count, dest + count));
+        }
+    }
+}
@@ -0,0 +1,63 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the `ec_ctrl` syscall.
+
+use crate::capability::CapSel;
+use crate::error::HedronError;
+use crate::syscall::{encode_arg1, generic_syscall, SyscallNum};
+use crate::NUM_CAP_SEL;
+
+/// Sub-operations of the `ec_ctrl` syscall.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum EcCtrlOp {
+    /// Forces the EC to enter the kernel. Before the EC returns to user space, the kernel
+    /// calls the portal for the recall exception of the EC.
+    Recall = 0,
+}
+
+impl EcCtrlOp {
+    /// Returns the raw unsigned integer value.
+    pub fn val(self) -> u8 {
+        self as u8
+    }
+}
+
+/// System call `ec_ctrl` with the `RECALL` operation. Interrupts the execution of the given
+/// EC, for example to preempt it or to inspect its state via the recall portal. The capability
+/// needs the [`crate::capability::ECCapPermissions::EC_CTRL`] permission.
+pub fn ec_ctrl_recall(ec_sel: CapSel) -> Result<(), HedronError> {
+    assert!(
+        ec_sel < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+
+    let arg1 = encode_arg1(SyscallNum::EcTrl, EcCtrlOp::Recall.val(), ec_sel);
+
+    unsafe {
+        generic_syscall(arg1, 0, 0, 0, 0)
+            .map(|_x| ())
+            .map_err(|e| e.0)
+    }
+}
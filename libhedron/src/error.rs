@@ -0,0 +1,56 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! The error type of this crate. See [`HedronError`].
+
+use crate::syscall::SyscallStatus;
+
+/// Error of a syscall wrapper or of decoding a raw value of the Hedron ABI. Unknown values are
+/// preserved instead of causing a panic, because newer versions of Hedron may add them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HedronError {
+    /// A syscall failed with a status that is known to this crate.
+    Syscall(SyscallStatus),
+    /// A syscall reported a status that is unknown to this crate. Contains the raw status.
+    UnknownStatus(u64),
+    /// The raw value is not a valid [`crate::capability::CrdKind`].
+    InvalidCrdKind(u8),
+    /// The raw permission bits contain bits that are not defined for the kind of capability.
+    InvalidPermissions(u8),
+}
+
+impl HedronError {
+    /// Returns the status of a failed syscall, if it is known to this crate.
+    pub fn status(self) -> Option<SyscallStatus> {
+        match self {
+            Self::Syscall(status) => Some(status),
+            _ => None,
+        }
+    }
+}
+
+impl From<SyscallStatus> for HedronError {
+    fn from(status: SyscallStatus) -> Self {
+        Self::Syscall(status)
+    }
+}
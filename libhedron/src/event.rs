@@ -0,0 +1,156 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Events that Hedron delivers through portals. Every EC has an event base (`evt_base`, see
+//! [`crate::create_ec::create_ec`]). For an event, the kernel calls the portal at the
+//! capability selector `evt_base + offset` in the capability space of the PD of the EC.
+//! There are [`crate::hip::Hip::num_exc_sel`] such selectors.
+
+use crate::capability::CapSel;
+
+/// Number of event selectors of an EC on x86_64. This is what the kernel reports as
+/// [`crate::hip::Hip::num_exc_sel`].
+pub const NUM_EVENTS: CapSel = 32;
+
+/// Offset of the portal that is called, when a global EC starts. The handler sets the
+/// initial instruction pointer and stack pointer.
+pub const STARTUP_EVENT_OFFSET: CapSel = 0x1e;
+
+/// Offset of the portal that is called, when an EC was recalled.
+/// See [`crate::ec_ctrl::ec_ctrl_recall`].
+pub const RECALL_EVENT_OFFSET: CapSel = 0x1f;
+
+/// An event of an EC, i.e., an x86 exception or one of the events of the kernel.
+/// [`Event::offset`] is the offset of its portal relative to the event base.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// `#DE`
+    DivideError,
+    /// `#DB`
+    Debug,
+    /// `#BP`, caused by `int3`.
+    Breakpoint,
+    /// `#OF`
+    Overflow,
+    /// `#BR`
+    BoundRange,
+    /// `#UD`
+    InvalidOpcode,
+    /// `#NM`
+    DeviceNotAvailable,
+    /// `#DF`
+    DoubleFault,
+    /// `#TS`
+    InvalidTss,
+    /// `#NP`
+    SegmentNotPresent,
+    /// `#SS`
+    StackSegmentFault,
+    /// `#GP`
+    GeneralProtection,
+    /// `#PF`. The fault address is in the second word of the exit qualification.
+    PageFault,
+    /// `#MF`
+    FloatingPoint,
+    /// `#AC`
+    AlignmentCheck,
+    /// `#MC`
+    MachineCheck,
+    /// `#XM`
+    SimdFloatingPoint,
+    /// See [`STARTUP_EVENT_OFFSET`].
+    Startup,
+    /// See [`RECALL_EVENT_OFFSET`].
+    Recall,
+    /// Any other (reserved) vector.
+    Other(u8),
+}
+
+impl Event {
+    /// Returns the event for the offset relative to the event base.
+    pub fn from_offset(offset: CapSel) -> Self {
+        match offset {
+            0 => Self::DivideError,
+            1 => Self::Debug,
+            3 => Self::Breakpoint,
+            4 => Self::Overflow,
+            5 => Self::BoundRange,
+            6 => Self::InvalidOpcode,
+            7 => Self::DeviceNotAvailable,
+            8 => Self::DoubleFault,
+            10 => Self::InvalidTss,
+            11 => Self::SegmentNotPresent,
+            12 => Self::StackSegmentFault,
+            13 => Self::GeneralProtection,
+            14 => Self::PageFault,
+            16 => Self::FloatingPoint,
+            17 => Self::AlignmentCheck,
+            18 => Self::MachineCheck,
+            19 => Self::SimdFloatingPoint,
+            STARTUP_EVENT_OFFSET => Self::Startup,
+            RECALL_EVENT_OFFSET => Self::Recall,
+            offset => Self::Other(offset as u8),
+        }
+    }
+
+    /// Returns the offset of the portal relative to the event base.
+    pub fn offset(self) -> CapSel {
+        match self {
+            Self::DivideError => 0,
+            Self::Debug => 1,
+            Self::Breakpoint => 3,
+            Self::Overflow => 4,
+            Self::BoundRange => 5,
+            Self::InvalidOpcode => 6,
+            Self::DeviceNotAvailable => 7,
+            Self::DoubleFault => 8,
+            Self::InvalidTss => 10,
+            Self::SegmentNotPresent => 11,
+            Self::StackSegmentFault => 12,
+            Self::GeneralProtection => 13,
+            Self::PageFault => 14,
+            Self::FloatingPoint => 16,
+            Self::AlignmentCheck => 17,
+            Self::MachineCheck => 18,
+            Self::SimdFloatingPoint => 19,
+            Self::Startup => STARTUP_EVENT_OFFSET,
+            Self::Recall => RECALL_EVENT_OFFSET,
+            Self::Other(offset) => offset as CapSel,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_offsets() {
+        for offset in 0..NUM_EVENTS {
+            assert_eq!(Event::from_offset(offset).offset(), offset);
+        }
+        assert_eq!(Event::from_offset(14), Event::PageFault);
+        assert_eq!(Event::from_offset(0x1e), Event::Startup);
+        assert_eq!(Event::from_offset(2), Event::Other(2));
+    }
+}
@@ -0,0 +1,637 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the Hypervisor Information Page (HIP).
+//!
+//! Hedron maps the HIP read-only into the address space of the roottask and passes
+//! a pointer to it in `rsp` during startup. It describes the platform: CPUs, memory
+//! regions, boot modules, and the layout of the capability space. The HIP consists of
+//! a fixed header followed by three arrays of descriptors (CPUs, IOAPICs, and memory), whose
+//! offsets and strides are part of the header. Hence, this module only relies on the header
+//! layout and uses the offsets to find the descriptors. The layout follows `hip.hpp` of
+//! Hedron, which differs from NOVA by the IOAPIC descriptors.
+
+use core::fmt::{Debug, Formatter};
+use core::mem::size_of;
+
+/// Signature of the HIP. The ASCII string "NOVA" in little endian.
+pub const HIP_SIGNATURE: u32 = 0x41564f4e;
+
+/// Errors that can happen when [`Hip::from_ptr`] validates the HIP.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HipError {
+    /// The pointer is null or not properly aligned.
+    NullOrMisaligned,
+    /// The signature doesn't match [`HIP_SIGNATURE`]. Contains the found signature.
+    WrongSignature(u32),
+    /// The 16-bit checksum over the whole HIP is not zero.
+    ChecksumMismatch,
+    /// The offsets or sizes of the descriptor arrays don't fit into the HIP.
+    InvalidLayout,
+}
+
+/// The header of the Hypervisor Information Page of Hedron. The descriptor arrays are
+/// accessed via [`Hip::cpus`], [`Hip::ioapics`], and [`Hip::mem_descs`]. They follow each
+/// other in this order.
+#[derive(Debug)]
+#[repr(C)]
+pub struct Hip {
+    signature: u32,
+    checksum: u16,
+    length: u16,
+    cpu_desc_offset: u16,
+    cpu_desc_size: u16,
+    ioapic_desc_offset: u16,
+    ioapic_desc_size: u16,
+    mem_desc_offset: u16,
+    mem_desc_size: u16,
+    feature_flags: u32,
+    api_version: u32,
+    sel_num: u32,
+    sel_exc: u32,
+    sel_vmi: u32,
+    sel_gsi: u32,
+    cfg_page: u32,
+    cfg_utcb: u32,
+    freq_tsc: u32,
+    freq_bus: u32,
+}
+
+impl Hip {
+    /// Validates the HIP behind the pointer and returns a reference to it.
+    /// Checks the signature, the checksum, and that the descriptor arrays are
+    /// within the bounds of the HIP.
+    ///
+    /// # Safety
+    /// The pointer must point to the mapped HIP, i.e., the value that Hedron
+    /// passes to the roottask. The memory must stay mapped and unchanged.
+    pub unsafe fn from_ptr(ptr: *const u8) -> Result<&'static Self, HipError> {
+        if ptr.is_null() || ptr as usize % core::mem::align_of::<Self>() != 0 {
+            return Err(HipError::NullOrMisaligned);
+        }
+        let hip = &*(ptr as *const Self);
+
+        if hip.signature != HIP_SIGNATURE {
+            return Err(HipError::WrongSignature(hip.signature));
+        }
+
+        let length = hip.length as usize;
+        if length < size_of::<Self>() || length % 2 != 0 {
+            return Err(HipError::InvalidLayout);
+        }
+
+        // The checksum field is chosen by Hedron so that the sum of all
+        // 16-bit words of the HIP is zero.
+        let words = core::slice::from_raw_parts(ptr as *const u16, length / 2);
+        let checksum = words.iter().fold(0_u16, |acc, w| acc.wrapping_add(*w));
+        if checksum != 0 {
+            return Err(HipError::ChecksumMismatch);
+        }
+
+        hip.check_layout()?;
+        Ok(hip)
+    }
+
+    /// Checks that the descriptor arrays follow each other inside the HIP and that each
+    /// descriptor is at least as large as its Rust representation.
+    fn check_layout(&self) -> Result<(), HipError> {
+        let length = self.length as usize;
+        let cpu_offset = self.cpu_desc_offset as usize;
+        let ioapic_offset = self.ioapic_desc_offset as usize;
+        let mem_offset = self.mem_desc_offset as usize;
+        let cpu_size = self.cpu_desc_size as usize;
+        let ioapic_size = self.ioapic_desc_size as usize;
+        let mem_size = self.mem_desc_size as usize;
+
+        let layout_valid = cpu_offset >= size_of::<Self>()
+            && cpu_offset <= ioapic_offset
+            && ioapic_offset <= mem_offset
+            && mem_offset <= length
+            && cpu_size >= size_of::<HipCpu>()
+            && ioapic_size >= size_of::<HipIoApic>()
+            && ioapic_offset % core::mem::align_of::<HipIoApic>() == 0
+            && ioapic_size % core::mem::align_of::<HipIoApic>() == 0
+            && mem_size >= size_of::<HipMem>()
+            && mem_offset % core::mem::align_of::<HipMem>() == 0
+            && mem_size % core::mem::align_of::<HipMem>() == 0;
+
+        if layout_valid {
+            Ok(())
+        } else {
+            Err(HipError::InvalidLayout)
+        }
+    }
+
+    /// Returns the raw API version of the kernel.
+    pub fn api_version(&self) -> u32 {
+        self.api_version
+    }
+
+    /// Returns the features of the platform that the kernel uses.
+    pub fn feature_flags(&self) -> HipFeatureFlags {
+        HipFeatureFlags::from_bits_truncate(self.feature_flags)
+    }
+
+    /// Returns the total length of the HIP in bytes.
+    pub fn length(&self) -> u16 {
+        self.length
+    }
+
+    /// Returns the number of available capability selectors in each
+    /// capability space for kernel objects.
+    pub fn num_cap_sel(&self) -> u64 {
+        self.sel_num as u64
+    }
+
+    /// Returns the number of capability selectors used for exception handling.
+    /// The selectors `evt_base..(evt_base + num_exc_sel)` of an EC refer to the portals
+    /// that are called for exceptions.
+    pub fn num_exc_sel(&self) -> u64 {
+        self.sel_exc as u64
+    }
+
+    /// Returns the number of capability selectors used for VM exit handling.
+    pub fn num_vmi_sel(&self) -> u64 {
+        self.sel_vmi as u64
+    }
+
+    /// Returns the number of global system interrupts (GSIs).
+    pub fn num_gsi_sel(&self) -> u64 {
+        self.sel_gsi as u64
+    }
+
+    /// Returns the bitmask of the supported page sizes. Bit `n` is set, if pages
+    /// of size `2^n` bytes are supported.
+    pub fn page_sizes(&self) -> u32 {
+        self.cfg_page
+    }
+
+    /// Returns the bitmask of the supported UTCB sizes. Bit `n` is set, if UTCBs
+    /// of size `2^n` bytes are supported.
+    pub fn utcb_sizes(&self) -> u32 {
+        self.cfg_utcb
+    }
+
+    /// Returns the frequency of the TSC in kHz.
+    pub fn tsc_freq_khz(&self) -> u32 {
+        self.freq_tsc
+    }
+
+    /// Returns the frequency of the bus in kHz.
+    pub fn bus_freq_khz(&self) -> u32 {
+        self.freq_bus
+    }
+
+    /// Returns an iterator over all CPU descriptors. This includes disabled CPUs,
+    /// see [`HipCpu::enabled`].
+    pub fn cpus(&self) -> HipDescIter<'_, HipCpu> {
+        HipDescIter::new(
+            self,
+            self.cpu_desc_offset,
+            self.ioapic_desc_offset,
+            self.cpu_desc_size,
+        )
+    }
+
+    /// Returns the number of CPU descriptor slots, including disabled CPUs. This is the
+    /// maximum number of CPUs that the kernel was built for (`NUM_CPU` in Hedron).
+    pub fn num_cpu_slots(&self) -> u64 {
+        ((self.ioapic_desc_offset - self.cpu_desc_offset) / self.cpu_desc_size) as u64
+    }
+
+    /// Returns the number of enabled CPUs.
+    pub fn num_cpus(&self) -> usize {
+        self.cpus().filter(|cpu| cpu.enabled()).count()
+    }
+
+    /// Returns an iterator over the descriptors of all IOAPICs.
+    pub fn ioapics(&self) -> HipDescIter<'_, HipIoApic> {
+        HipDescIter::new(
+            self,
+            self.ioapic_desc_offset,
+            self.mem_desc_offset,
+            self.ioapic_desc_size,
+        )
+    }
+
+    /// Returns an iterator over all memory descriptors. This includes the memory
+    /// map of the firmware, the memory of the hypervisor, and the boot modules.
+    pub fn mem_descs(&self) -> HipDescIter<'_, HipMem> {
+        HipDescIter::new(self, self.mem_desc_offset, self.length, self.mem_desc_size)
+    }
+
+    /// Returns an iterator over the memory descriptors of all boot modules, in the order
+    /// in which the bootloader passed them. The first one is the roottask.
+    pub fn boot_modules(&self) -> impl Iterator<Item = &HipMem> {
+        self.mem_descs()
+            .filter(|mem| mem.typ() == HipMemType::MultibootModule)
+    }
+}
+
+bitflags::bitflags! {
+    /// Features of the platform that are reported by the kernel in the HIP.
+    pub struct HipFeatureFlags: u32 {
+        /// The kernel uses an IOMMU.
+        const IOMMU = 1 << 0;
+        /// The kernel supports VMX (Intel VT-x).
+        const VMX = 1 << 1;
+    }
+}
+
+/// Descriptor of a CPU inside the HIP.
+#[derive(Debug)]
+#[repr(C)]
+pub struct HipCpu {
+    flags: u8,
+    thread: u8,
+    core: u8,
+    package: u8,
+    acpi_id: u8,
+    _reserved: [u8; 3],
+}
+
+impl HipCpu {
+    /// Whether the CPU is enabled and usable by the kernel.
+    pub fn enabled(&self) -> bool {
+        self.flags & 1 != 0
+    }
+
+    /// Returns the thread ID inside the core.
+    pub fn thread(&self) -> u8 {
+        self.thread
+    }
+
+    /// Returns the core ID inside the package.
+    pub fn core(&self) -> u8 {
+        self.core
+    }
+
+    /// Returns the package ID.
+    pub fn package(&self) -> u8 {
+        self.package
+    }
+
+    /// Returns the ACPI processor ID.
+    pub fn acpi_id(&self) -> u8 {
+        self.acpi_id
+    }
+}
+
+/// Descriptor of an IOAPIC inside the HIP.
+#[derive(Debug)]
+#[repr(C)]
+pub struct HipIoApic {
+    id: u32,
+    version: u32,
+    gsi_base: u32,
+    base: u32,
+}
+
+impl HipIoApic {
+    /// Returns the ID of the IOAPIC.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the version of the IOAPIC.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the first GSI that is connected to the IOAPIC.
+    pub fn gsi_base(&self) -> u32 {
+        self.gsi_base
+    }
+
+    /// Returns the physical address of the MMIO registers of the IOAPIC.
+    pub fn base(&self) -> u32 {
+        self.base
+    }
+}
+
+/// Type of a memory descriptor. Positive values are the types of the
+/// firmware memory map (Multiboot/E820). Negative values are defined by the kernel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HipMemType {
+    /// Usable RAM.
+    Available,
+    /// Reserved memory.
+    Reserved,
+    /// Memory with ACPI tables that can be reclaimed after they were parsed.
+    AcpiReclaimable,
+    /// ACPI non-volatile storage.
+    AcpiNvs,
+    /// Defective RAM.
+    BadMemory,
+    /// Memory used by the hypervisor.
+    Hypervisor,
+    /// A Multiboot boot module. The first one is the roottask.
+    MultibootModule,
+    /// Any other (unknown) type.
+    Other(i32),
+}
+
+impl From<i32> for HipMemType {
+    fn from(val: i32) -> Self {
+        match val {
+            1 => Self::Available,
+            2 => Self::Reserved,
+            3 => Self::AcpiReclaimable,
+            4 => Self::AcpiNvs,
+            5 => Self::BadMemory,
+            -1 => Self::Hypervisor,
+            -2 => Self::MultibootModule,
+            val => Self::Other(val),
+        }
+    }
+}
+
+/// Descriptor of a physical memory region inside the HIP.
+#[repr(C)]
+pub struct HipMem {
+    addr: u64,
+    size: u64,
+    typ: i32,
+    aux: u32,
+}
+
+impl HipMem {
+    /// Returns the physical start address of the region.
+    pub fn addr(&self) -> u64 {
+        self.addr
+    }
+
+    /// Returns the size of the region in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the physical end address (exclusive) of the region.
+    pub fn end(&self) -> u64 {
+        self.addr + self.size
+    }
+
+    /// Returns the [`HipMemType`] of the region.
+    pub fn typ(&self) -> HipMemType {
+        HipMemType::from(self.typ)
+    }
+
+    /// Returns the auxiliary value. For boot modules, this is the physical
+    /// address of the command line string.
+    pub fn aux(&self) -> u32 {
+        self.aux
+    }
+}
+
+impl Debug for HipMem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("HipMem")
+            // trick: print as pointer => print as hex
+            .field("addr", &(self.addr as *const u8))
+            .field("size", &(self.size as *const u8))
+            .field("typ", &self.typ())
+            .field("aux", &(self.aux as *const u8))
+            .finish()
+    }
+}
+
+/// Iterator over the descriptors of an array inside the HIP. The stride is
+/// taken from the HIP and can be bigger than the size of `T`, if the kernel
+/// reports more fields than are modelled here.
+pub struct HipDescIter<'a, T> {
+    hip: &'a Hip,
+    offset: usize,
+    end: usize,
+    stride: usize,
+    _zst: core::marker::PhantomData<&'a T>,
+}
+
+impl<'a, T> HipDescIter<'a, T> {
+    fn new(hip: &'a Hip, offset: u16, end: u16, stride: u16) -> Self {
+        Self {
+            hip,
+            offset: offset as usize,
+            end: end as usize,
+            stride: stride as usize,
+            _zst: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<'a, T: 'a> Iterator for HipDescIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stride == 0 || self.offset + self.stride > self.end {
+            return None;
+        }
+        let ptr = self.hip as *const Hip as *const u8;
+        // safe because Hip::from_ptr validated the layout of the arrays
+        let desc = unsafe { &*(ptr.add(self.offset) as *const T) };
+        self.offset += self.stride;
+        Some(desc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Size of the header in `hip.hpp` of Hedron.
+    const HEADER_SIZE: usize = 0x3c;
+    const CPU_OFFSET: usize = HEADER_SIZE;
+    const IOAPIC_OFFSET: usize = CPU_OFFSET + 2 * 8;
+    /// The compiler aligns the memory descriptors to 8 bytes.
+    const MEM_OFFSET: usize = (IOAPIC_OFFSET + 16 + 7) & !7;
+    const LENGTH: usize = MEM_OFFSET + 2 * 24;
+
+    /// Backing memory of a HIP with the required alignment.
+    #[repr(C, align(8))]
+    struct HipBytes([u8; LENGTH]);
+
+    impl HipBytes {
+        /// A valid HIP with two CPU slots, of which only the first is enabled, one IOAPIC,
+        /// available memory, and a boot module. The offsets are taken from `hip.hpp` of
+        /// Hedron and not from [`Hip`] on purpose.
+        fn new() -> Self {
+            let mut hip = Self([0; LENGTH]);
+            hip.write(0x0, &HIP_SIGNATURE.to_le_bytes());
+            hip.write(0x6, &(LENGTH as u16).to_le_bytes());
+            hip.write(0x8, &(CPU_OFFSET as u16).to_le_bytes());
+            hip.write(0xa, &8_u16.to_le_bytes());
+            hip.write(0xc, &(IOAPIC_OFFSET as u16).to_le_bytes());
+            hip.write(0xe, &16_u16.to_le_bytes());
+            hip.write(0x10, &(MEM_OFFSET as u16).to_le_bytes());
+            hip.write(0x12, &24_u16.to_le_bytes());
+            hip.write(0x14, &3_u32.to_le_bytes());
+            hip.write(0x18, &0x1234_u32.to_le_bytes());
+            hip.write(0x1c, &0x1000_u32.to_le_bytes());
+            hip.write(0x20, &0x20_u32.to_le_bytes());
+            hip.write(0x24, &0x100_u32.to_le_bytes());
+            hip.write(0x28, &24_u32.to_le_bytes());
+            hip.write(0x2c, &(1_u32 << 12 | 1 << 21).to_le_bytes());
+            hip.write(0x30, &(1_u32 << 12).to_le_bytes());
+            hip.write(0x34, &2_000_000_u32.to_le_bytes());
+            hip.write(0x38, &100_000_u32.to_le_bytes());
+            // flags, thread, core, package, acpi_id
+            hip.write(CPU_OFFSET, &[1, 0, 0, 0, 0]);
+            hip.write(CPU_OFFSET + 8, &[0, 0, 1, 0, 1]);
+            // id, version, gsi_base, base
+            hip.write(IOAPIC_OFFSET, &1_u32.to_le_bytes());
+            hip.write(IOAPIC_OFFSET + 4, &0x20_u32.to_le_bytes());
+            hip.write(IOAPIC_OFFSET + 8, &0_u32.to_le_bytes());
+            hip.write(IOAPIC_OFFSET + 12, &0xfec0_0000_u32.to_le_bytes());
+            hip.write_mem(MEM_OFFSET, 0x10_0000, 0x100_0000, 1, 0);
+            hip.write_mem(MEM_OFFSET + 24, 0x20_0000, 0x1_0000, -2, 0x30_0000);
+            hip.fix_checksum();
+            hip
+        }
+
+        fn write(&mut self, offset: usize, val: &[u8]) {
+            self.0[offset..offset + val.len()].copy_from_slice(val);
+        }
+
+        fn write_mem(&mut self, offset: usize, addr: u64, size: u64, typ: i32, aux: u32) {
+            self.write(offset, &addr.to_le_bytes());
+            self.write(offset + 8, &size.to_le_bytes());
+            self.write(offset + 16, &typ.to_le_bytes());
+            self.write(offset + 20, &aux.to_le_bytes());
+        }
+
+        /// Sets the checksum, so that the sum of all 16-bit words is zero.
+        fn fix_checksum(&mut self) {
+            self.write(0x4, &[0, 0]);
+            let sum = self.0.chunks(2).fold(0_u16, |acc, w| {
+                acc.wrapping_add(u16::from_le_bytes([w[0], w[1]]))
+            });
+            self.write(0x4, &0_u16.wrapping_sub(sum).to_le_bytes());
+        }
+
+        fn parse(&self) -> Result<&Hip, HipError> {
+            unsafe { Hip::from_ptr(self.0.as_ptr()) }
+        }
+    }
+
+    #[test]
+    fn test_header_fields() {
+        let bytes = HipBytes::new();
+        let hip = bytes.parse().unwrap();
+        assert_eq!(size_of::<Hip>(), HEADER_SIZE);
+        assert_eq!(hip.length() as usize, LENGTH);
+        assert_eq!(
+            hip.feature_flags(),
+            HipFeatureFlags::IOMMU | HipFeatureFlags::VMX
+        );
+        assert_eq!(hip.api_version(), 0x1234);
+        assert_eq!(hip.num_cap_sel(), 0x1000);
+        assert_eq!(hip.num_exc_sel(), 0x20);
+        assert_eq!(hip.num_vmi_sel(), 0x100);
+        assert_eq!(hip.num_gsi_sel(), 24);
+        assert_eq!(hip.page_sizes(), 1 << 12 | 1 << 21);
+        assert_eq!(hip.utcb_sizes(), 1 << 12);
+        assert_eq!(hip.tsc_freq_khz(), 2_000_000);
+        assert_eq!(hip.bus_freq_khz(), 100_000);
+    }
+
+    #[test]
+    fn test_descriptor_iterators() {
+        let bytes = HipBytes::new();
+        let hip = bytes.parse().unwrap();
+
+        // the IOAPIC descriptor must not be read as a CPU
+        assert_eq!(hip.cpus().count(), 2);
+        assert_eq!(hip.num_cpu_slots(), 2);
+        assert_eq!(hip.num_cpus(), 1);
+        let cpu = hip.cpus().nth(1).unwrap();
+        assert!(!cpu.enabled());
+        assert_eq!((cpu.core(), cpu.acpi_id()), (1, 1));
+
+        assert_eq!(hip.ioapics().count(), 1);
+        let ioapic = hip.ioapics().next().unwrap();
+        assert_eq!(ioapic.id(), 1);
+        assert_eq!(ioapic.version(), 0x20);
+        assert_eq!(ioapic.gsi_base(), 0);
+        assert_eq!(ioapic.base(), 0xfec0_0000);
+
+        assert_eq!(hip.mem_descs().count(), 2);
+        let mem = hip.mem_descs().next().unwrap();
+        assert_eq!(mem.typ(), HipMemType::Available);
+        assert_eq!(mem.end(), 0x110_0000);
+        assert_eq!(hip.boot_modules().count(), 1);
+        let module = hip.boot_modules().next().unwrap();
+        assert_eq!(module.addr(), 0x20_0000);
+        assert_eq!(module.aux(), 0x30_0000);
+    }
+
+    #[test]
+    fn test_from_ptr_rejects_invalid_pointers() {
+        let bytes = HipBytes::new();
+        assert_eq!(
+            unsafe { Hip::from_ptr(core::ptr::null()) }.unwrap_err(),
+            HipError::NullOrMisaligned
+        );
+        assert_eq!(
+            unsafe { Hip::from_ptr(bytes.0.as_ptr().add(2)) }.unwrap_err(),
+            HipError::NullOrMisaligned
+        );
+    }
+
+    #[test]
+    fn test_from_ptr_rejects_wrong_signature() {
+        let mut bytes = HipBytes::new();
+        bytes.write(0x0, &0x1234_5678_u32.to_le_bytes());
+        bytes.fix_checksum();
+        assert_eq!(
+            bytes.parse().unwrap_err(),
+            HipError::WrongSignature(0x1234_5678)
+        );
+    }
+
+    #[test]
+    fn test_from_ptr_rejects_wrong_checksum() {
+        let mut bytes = HipBytes::new();
+        bytes.write(0x34, &1_u32.to_le_bytes());
+        assert_eq!(bytes.parse().unwrap_err(), HipError::ChecksumMismatch);
+    }
+
+    #[test]
+    fn test_from_ptr_rejects_invalid_layout() {
+        // the IOAPIC descriptors start inside the header
+        let mut bytes = HipBytes::new();
+        bytes.write(0x8, &0x10_u16.to_le_bytes());
+        bytes.fix_checksum();
+        assert_eq!(bytes.parse().unwrap_err(), HipError::InvalidLayout);
+
+        // the memory descriptors start before the IOAPIC descriptors
+        let mut bytes = HipBytes::new();
+        bytes.write(0x10, &(IOAPIC_OFFSET as u16 - 8).to_le_bytes());
+        bytes.fix_checksum();
+        assert_eq!(bytes.parse().unwrap_err(), HipError::InvalidLayout);
+
+        // the HIP is longer than its memory
+        let mut bytes = HipBytes::new();
+        bytes.write(0x6, &(HEADER_SIZE as u16 - 4).to_le_bytes());
+        bytes.fix_checksum();
+        assert_eq!(bytes.parse().unwrap_err(), HipError::InvalidLayout);
+    }
+}
@@ -0,0 +1,88 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the IPC syscalls `call` and `reply`.
+//!
+//! The message of an IPC call is transferred via the UTCB of the calling EC and the UTCB of
+//! the local EC that handles the portal. See [`crate::utcb::Utcb`] to marshal untyped
+//! items and typed items (delegations).
+//!
+//! A portal handler is a function of type [`PortalEntry`]. The kernel starts the local EC at
+//! the portal's entry with the [`PortalId`] in `rdi` and with the stack pointer that was set
+//! during `create_ec`. The stack is therefore reset on every portal invocation. A handler
+//! never returns but finishes with [`ipc_reply`].
+
+use crate::capability::CapSel;
+use crate::error::HedronError;
+use crate::pt_ctrl::PortalId;
+use crate::syscall::{encode_arg1, generic_syscall, SyscallNum};
+use crate::NUM_CAP_SEL;
+
+/// Signature of the entry function of a portal. The argument is the [`PortalId`]. The function
+/// must finish with [`ipc_reply`].
+pub type PortalEntry = extern "C" fn(PortalId) -> !;
+
+/// Returns the initial stack pointer for a local EC that executes a [`PortalEntry`] from
+/// the given top of its stack. The kernel jumps into the entry without pushing a return address.
+/// To comply with the System V ABI, the stack pointer must look like there was a `call`, i.e.,
+/// `rsp + 8` must be 16-byte aligned.
+pub fn portal_stack_ptr(stack_top: u64) -> u64 {
+    (stack_top & !0xf) - 8
+}
+
+/// System call `call`. Calls the portal and blocks until the local EC behind the portal
+/// replies. The message is taken from the UTCB of the calling EC and the reply is placed in
+/// the same UTCB. The capability needs the
+/// [`crate::capability::PTCapPermissions::CALL`] permission.
+pub fn ipc_call(pt_sel: CapSel) -> Result<(), HedronError> {
+    assert!(
+        pt_sel < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+
+    let arg1 = encode_arg1(SyscallNum::Call, 0, pt_sel);
+
+    unsafe {
+        generic_syscall(arg1, 0, 0, 0, 0)
+            .map(|_x| ())
+            .map_err(|e| e.0)
+    }
+}
+
+/// System call `reply`. Sends the message in the UTCB of the current local EC back to
+/// the caller of the portal and ends the current portal invocation. This never returns,
+/// because the local EC starts again at a portal entry with a fresh stack on the next call.
+/// Hence, nothing on the stack is dropped.
+pub fn ipc_reply() -> ! {
+    #[cfg(feature = "simulator")]
+    panic!("the simulator can't reply from a portal");
+    #[cfg(not(feature = "simulator"))]
+    unsafe {
+        core::arch::asm!(
+            "syscall",
+            "ud2",
+            in("rdi") SyscallNum::Reply.val(),
+            options(noreturn, nostack)
+        )
+    }
+}
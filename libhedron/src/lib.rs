@@ -0,0 +1,76 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Bindings for the ABI of the Hedron microhypervisor: capability range descriptors and
+//! permissions, syscalls, the Hypervisor Information Page (HIP), and the UTCB.
+//!
+//! The crate is `no_std` and has no coupling to a linker script or a specific roottask. The
+//! encoding and decoding logic is pure and can be tested on the host with `cargo test`.
+//! Only the syscalls themselves require to run under Hedron, unless the `simulator` feature
+//! replaces them with an in-process simulation. See [`syscall::SyscallBackend`].
+
+#![no_std]
+
+use crate::capability::CapSel;
+
+pub mod assign_gsi;
+pub mod assign_pci;
+pub mod capability;
+pub mod create_ec;
+pub mod create_pd;
+pub mod create_pt;
+pub mod create_sc;
+pub mod create_sm;
+pub mod delegate;
+pub mod ec_ctrl;
+pub mod error;
+pub mod event;
+pub mod hip;
+pub mod ipc;
+pub mod machine_ctrl;
+pub mod mtd;
+pub mod pd_ctrl;
+pub mod pt_ctrl;
+pub mod revoke;
+pub mod sc_ctrl;
+#[cfg(feature = "simulator")]
+pub mod simulator;
+pub mod sm_ctrl;
+pub mod syscall;
+pub mod utcb;
+
+/// Maximum of 2^26 = 67108864 capability selectors for kernel objects.
+/// Note that this number can be higher for memory capabilities!
+pub const NUM_CAP_SEL: CapSel = 67108864;
+
+/// By convention this is the capability selector of the roottask itself.
+pub const ROOTTASK_CAPSEL: CapSel = 32;
+
+/// By convention this is the capability selector of the initial EC of the roottask.
+pub const ROOTTASK_EC_CAPSEL: CapSel = ROOTTASK_CAPSEL + 1;
+
+/// By convention this is the capability selector of the initial SC of the roottask.
+pub const ROOTTASK_SC_CAPSEL: CapSel = ROOTTASK_CAPSEL + 2;
+
+/// Size of a page in bytes. Memory capabilities refer to page numbers of this size.
+pub const PAGE_SIZE: u64 = 4096;
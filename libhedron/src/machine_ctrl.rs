@@ -0,0 +1,94 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the `machine_ctrl` syscall.
+
+use crate::error::HedronError;
+use crate::syscall::{generic_syscall, SyscallNum};
+
+/// Sub-operations of the `machine_ctrl` syscall.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u64)]
+pub enum MachineCtrlSubSyscall {
+    /// Suspends the machine into an ACPI sleep state.
+    Suspend = 0,
+    /// Applies a microcode update on the current CPU.
+    UpdateMicrocode = 1,
+}
+
+impl MachineCtrlSubSyscall {
+    pub fn val(self) -> u64 {
+        self as u64
+    }
+}
+
+/// Encodes the first argument of the `machine_ctrl` syscall.
+fn encode_arg1(sub_syscall: MachineCtrlSubSyscall) -> u64 {
+    const SYSCALL_BITMASK: u64 = 0xff;
+    const SUB_SYSCALL_BITMASK: u64 = 0x300;
+    const SUB_SYSCALL_BITSHIFT: u64 = 8;
+
+    let mut arg1 = 0;
+    arg1 |= SyscallNum::MachineCtrl.val() & SYSCALL_BITMASK;
+    arg1 |= (sub_syscall.val() << SUB_SYSCALL_BITSHIFT) & SUB_SYSCALL_BITMASK;
+    arg1
+}
+
+/// System call `machine_ctrl_suspend` puts the machine into an ACPI sleep state, such as S3
+/// (suspend to RAM). The kernel saves its own state, parks all CPUs, and writes the sleep type
+/// into the PM1 control registers. When the machine resumes, the kernel restores its state and
+/// the syscall returns to the caller. The caller is responsible for preparing the devices and
+/// for parsing the ACPI tables to find the sleep type values of the sleep state (`\_Sx`).
+/// Only the roottask can use this syscall.
+///
+/// # Parameters
+/// - `slp_typ_a` The value for the `SLP_TYPa` field of the `PM1a_CNT` register.
+/// - `slp_typ_b` The value for the `SLP_TYPb` field of the `PM1b_CNT` register.
+pub fn machine_ctrl_suspend(slp_typ_a: u8, slp_typ_b: u8) -> Result<(), HedronError> {
+    let arg1 = encode_arg1(MachineCtrlSubSyscall::Suspend);
+    let arg2 = slp_typ_a as u64 | ((slp_typ_b as u64) << 8);
+
+    unsafe {
+        generic_syscall(arg1, arg2, 0, 0, 0)
+            .map(|_x| ())
+            .map_err(|e| e.0)
+    }
+}
+
+/// System call `machine_ctrl_update_microcode` loads a microcode update into the current CPU.
+/// Only the roottask can use this syscall.
+///
+/// # Parameters
+/// - `update` The microcode update including its header, as provided by the CPU vendor. It
+///   must be mapped in the address space of the caller.
+pub fn machine_ctrl_update_microcode(update: &[u8]) -> Result<(), HedronError> {
+    let arg1 = encode_arg1(MachineCtrlSubSyscall::UpdateMicrocode);
+    let arg2 = update.as_ptr() as u64;
+    let arg3 = update.len() as u64;
+
+    unsafe {
+        generic_syscall(arg1, arg2, arg3, 0, 0)
+            .map(|_x| ())
+            .map_err(|e| e.0)
+    }
+}
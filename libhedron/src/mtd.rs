@@ -0,0 +1,84 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the Message Transfer Descriptor (MTD).
+
+bitflags::bitflags! {
+    /// The Message Transfer Descriptor (MTD) of a portal. It specifies which parts of the
+    /// architectural state of the calling EC the kernel transfers into the UTCB of the
+    /// handler EC, when the kernel calls the portal for an exception, a VM exit, or the
+    /// startup of a global EC. During the reply, the MTD in the UTCB specifies which parts
+    /// of the state are written back. Ignored for regular IPC.
+    pub struct Mtd: u64 {
+        /// General purpose registers RAX, RCX, RDX, and RBX.
+        const GPR_ACDB = 1 << 0;
+        /// General purpose registers RBP, RSI, and RDI.
+        const GPR_BSD = 1 << 1;
+        /// Stack pointer.
+        const RSP = 1 << 2;
+        /// Instruction pointer and instruction length.
+        const RIP_LEN = 1 << 3;
+        /// RFLAGS register.
+        const RFLAGS = 1 << 4;
+        /// DS and ES segment registers.
+        const DS_ES = 1 << 5;
+        /// FS and GS segment registers.
+        const FS_GS = 1 << 6;
+        /// CS and SS segment registers.
+        const CS_SS = 1 << 7;
+        /// Task register.
+        const TR = 1 << 8;
+        /// Local descriptor table register.
+        const LDTR = 1 << 9;
+        /// Global descriptor table register.
+        const GDTR = 1 << 10;
+        /// Interrupt descriptor table register.
+        const IDTR = 1 << 11;
+        /// Control registers.
+        const CR = 1 << 12;
+        /// Debug registers.
+        const DR = 1 << 13;
+        /// SYSENTER MSRs.
+        const SYSENTER = 1 << 14;
+        /// Exit qualification, i.e., error code and fault address of an exception.
+        const QUAL = 1 << 15;
+        /// Execution controls (VM exits only).
+        const CTRL = 1 << 16;
+        /// Event injection (VM exits only).
+        const INJ = 1 << 17;
+        /// Interruptibility and activity state (VM exits only).
+        const STA = 1 << 18;
+        /// Time stamp counter.
+        const TSC = 1 << 19;
+        /// EFER and PAT MSRs.
+        const EFER_PAT = 1 << 20;
+        /// Page directory pointer table entries.
+        const PDPTE = 1 << 21;
+        /// General purpose registers R8 to R15.
+        const GPR_R8_R15 = 1 << 22;
+        /// SYSCALL and SWAPGS MSRs.
+        const SYSCALL_SWAPGS = 1 << 23;
+        /// All general purpose registers.
+        const GPRS = Self::GPR_ACDB.bits | Self::GPR_BSD.bits | Self::GPR_R8_R15.bits;
+    }
+}
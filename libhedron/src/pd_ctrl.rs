@@ -0,0 +1,449 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the PD_CTRL syscall and its sub-syscalls PD_CTRL_DELEGATE and PD_CTRL_MSR_ACCESS.
+
+use crate::capability::{CapSel, Crd};
+use crate::error::HedronError;
+use crate::syscall::{generic_syscall, PdCtrlSubSyscall, SyscallNum, SyscallStatus};
+use crate::NUM_CAP_SEL;
+use core::fmt::{Debug, Formatter};
+
+/// Kind of a typed item. Encoded in bit 0 of [`DelegateFlags`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TypedItemKind {
+    /// Translates a capability of the receiver into its capability selector. Will eventually
+    /// be removed from Hedron.
+    Translate = 0,
+    /// Delegates a capability to the receiver.
+    Delegate = 1,
+}
+
+/// Carries additional infos for a transfer or delegation call including some flags.
+/// Can also be understood typed item
+/// (Partly described by 4.6.2.2 Typed Items of original NOVA spec.)
+///
+/// Use [`DelegateFlags::builder`] to construct a value and the getters to decode one, for
+/// example the flags of a received [`crate::utcb::TypedItem`].
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct DelegateFlags(u64);
+
+impl DelegateFlags {
+    const KIND_BIT: u64 = 1 << 0;
+    /// This flag is inverted in Hedron: set means "not into the host page table".
+    const NO_HOST_BIT: u64 = 1 << 8;
+    const DEVICE_BIT: u64 = 1 << 9;
+    const GUEST_BIT: u64 = 1 << 10;
+    const HYPERVISOR_BIT: u64 = 1 << 11;
+    const HOTSPOT_LEFT_SHIFT: u64 = 12;
+    /// Maximum value of the hotspot: all bits from bit 12 upwards.
+    pub const MAX_HOTSPOT: u64 = u64::MAX >> Self::HOTSPOT_LEFT_SHIFT;
+
+    /// # Parameters
+    /// - `in_host_pt` Mapping needs to go into host page table / I/O space. Only valid for memory and I/O delegations.
+    /// - `in_device_pt` Mapping needs to go into device page table. Only valid for memory delegations.
+    /// - `in_guest_pt` Mapping needs to go into guest page table / IO space. Valid for memory and I/O delegations.
+    /// - `use_hypervisor_as_src` Source is actually hypervisor PD. Only valid when used by the roottask, silently ignored otherwise.
+    /// - `hotspot` A hotspot is used to disambiguate send and receive windows for
+    ///             delegations. The hotspot carries additional information for some types
+    ///             of mappings as well.
+    ///
+    /// Prefer [`Self::builder`], which names each flag at the call site.
+    pub fn new(
+        in_host_pt: bool,
+        in_device_pt: bool,
+        in_guest_pt: bool,
+        use_hypervisor_as_src: bool,
+        hotspot: u64,
+    ) -> Self {
+        Self::builder()
+            .host(in_host_pt)
+            .device(in_device_pt)
+            .guest(in_guest_pt)
+            .hypervisor(use_hypervisor_as_src)
+            .hotspot(hotspot)
+            .build()
+    }
+
+    /// Returns a builder that starts with the values of [`Self::default`].
+    pub fn builder() -> DelegateFlagsBuilder {
+        DelegateFlagsBuilder::default()
+    }
+
+    /// Decodes the raw value, for example from a typed item of a received message.
+    pub fn from_val(val: u64) -> Self {
+        Self(val)
+    }
+
+    pub fn val(self) -> u64 {
+        self.0
+    }
+
+    /// Returns the kind of the typed item.
+    pub fn kind(self) -> TypedItemKind {
+        if self.0 & Self::KIND_BIT != 0 {
+            TypedItemKind::Delegate
+        } else {
+            TypedItemKind::Translate
+        }
+    }
+
+    /// Mapping needs to go into the host page table / I/O space. Only valid for memory and I/O delegations.
+    pub fn host(self) -> bool {
+        self.0 & Self::NO_HOST_BIT == 0
+    }
+
+    /// Mapping needs to go into the guest page table / IO space. Valid for memory and I/O delegations.
+    pub fn guest(self) -> bool {
+        self.0 & Self::GUEST_BIT != 0
+    }
+
+    /// Mapping needs to go into the device page table. Only valid for memory delegations.
+    pub fn device(self) -> bool {
+        self.0 & Self::DEVICE_BIT != 0
+    }
+
+    /// Source is actually hypervisor PD. Only valid when used by the roottask, silently ignored otherwise
+    pub fn hypervisor(self) -> bool {
+        self.0 & Self::HYPERVISOR_BIT != 0
+    }
+
+    /// The hotspot used to disambiguate send and receive windows.
+    pub fn hotspot(self) -> u64 {
+        self.0 >> Self::HOTSPOT_LEFT_SHIFT
+    }
+}
+
+impl Default for DelegateFlags {
+    /// Default is useful for "default" IO and mem delegations and all other delegations,
+    /// because it is ignored there silently.
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl Debug for DelegateFlags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DelegateFlags")
+            // trick: print as pointer => print as hex
+            .field("val (u64)", &(self.0 as *const u64))
+            .field("kind", &self.kind())
+            .field("host", &self.host())
+            .field("device", &self.device())
+            .field("guest", &self.guest())
+            .field("hypervisor", &self.hypervisor())
+            .field("hotspot", &(self.hotspot() as *const u64))
+            .finish()
+    }
+}
+
+/// Builder for [`DelegateFlags`]. Starts with a delegation into the host page table / I/O
+/// space with all other flags unset and hotspot 0.
+#[derive(Debug, Copy, Clone)]
+pub struct DelegateFlagsBuilder {
+    kind: TypedItemKind,
+    host: bool,
+    device: bool,
+    guest: bool,
+    hypervisor: bool,
+    hotspot: u64,
+}
+
+impl DelegateFlagsBuilder {
+    /// Sets the kind of the typed item.
+    pub fn kind(mut self, kind: TypedItemKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Mapping needs to go into the host page table / I/O space. Only valid for memory and I/O delegations.
+    pub fn host(mut self, host: bool) -> Self {
+        self.host = host;
+        self
+    }
+
+    /// Mapping needs to go into the device page table. Only valid for memory delegations.
+    pub fn device(mut self, device: bool) -> Self {
+        self.device = device;
+        self
+    }
+
+    /// Mapping needs to go into the guest page table / IO space. Valid for memory and I/O delegations.
+    pub fn guest(mut self, guest: bool) -> Self {
+        self.guest = guest;
+        self
+    }
+
+    /// Source is actually hypervisor PD. Only valid when used by the roottask, silently ignored otherwise.
+    pub fn hypervisor(mut self, hypervisor: bool) -> Self {
+        self.hypervisor = hypervisor;
+        self
+    }
+
+    /// A hotspot is used to disambiguate send and receive windows for delegations. The
+    /// hotspot carries additional information for some types of mappings as well.
+    pub fn hotspot(mut self, hotspot: u64) -> Self {
+        assert!(
+            hotspot <= DelegateFlags::MAX_HOTSPOT,
+            "hotspot {:#x} exceeds {:#x}",
+            hotspot,
+            DelegateFlags::MAX_HOTSPOT
+        );
+        self.hotspot = hotspot;
+        self
+    }
+
+    /// Encodes the flags.
+    pub fn build(self) -> DelegateFlags {
+        let mut base = self.kind as u64;
+        // this flag is inverted in Hedron
+        if !self.host {
+            base |= DelegateFlags::NO_HOST_BIT;
+        }
+        if self.device {
+            base |= DelegateFlags::DEVICE_BIT;
+        }
+        if self.guest {
+            base |= DelegateFlags::GUEST_BIT;
+        }
+        if self.hypervisor {
+            base |= DelegateFlags::HYPERVISOR_BIT;
+        }
+        base |= self.hotspot << DelegateFlags::HOTSPOT_LEFT_SHIFT;
+        DelegateFlags(base)
+    }
+}
+
+impl Default for DelegateFlagsBuilder {
+    fn default() -> Self {
+        Self {
+            // "Translate" (0x0) will eventually be removed. Therefore, delegate is the default.
+            kind: TypedItemKind::Delegate,
+            host: true,
+            device: false,
+            guest: false,
+            hypervisor: false,
+            hotspot: 0,
+        }
+    }
+}
+
+/// System call `pd_ctrl_delegate` transfers memory, port I/O and object capabilities
+/// from one protection domain to another. It allows the same functionality as rights
+/// delegation via IPC.
+///
+/// # Memory Delegations
+/// SrcCRD and DestCRD ([`crate::capability::CrdMem`]) refer to virtual page numbers. If the
+/// `hypervisor` flag of [`DelegateFlags`] is set and the source_pd is `0` (the one of the roottask),
+/// all memory is identity mapped. Hence, virtual address is physical address.
+///
+/// # Parameters
+/// - `source_crd` A [`Crd`] range descriptor describing the send window in the source PD.
+/// - `dest_crd` A [`Crd`] describing the receive window in the destination PD.
+pub fn pd_ctrl_delegate<Perm, Spec, ObjSpec>(
+    source_pd: CapSel,
+    dest_pd: CapSel,
+    source_crd: Crd<Perm, Spec, ObjSpec>,
+    dest_crd: Crd<Perm, Spec, ObjSpec>,
+    flags: DelegateFlags,
+) -> Result<(), HedronError> {
+    assert!(
+        source_pd < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+    assert!(
+        dest_pd < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+    const SYSCALL_BITMASK: u64 = 0xff;
+    const SUB_SYSCALL_BITMASK: u64 = 0x300;
+    const SUB_SYSCALL_BITSHIFT: u64 = 8;
+    const SOURCE_PD_BITMASK: u64 = !0x3ff;
+    const SOURCE_PD_BITSHIFT: u64 = 12;
+
+    let mut arg1 = 0;
+    arg1 |= SyscallNum::PdCtrl.val() & SYSCALL_BITMASK;
+    arg1 |= (PdCtrlSubSyscall::PdCtrlDelegate.val() << SUB_SYSCALL_BITSHIFT) & SUB_SYSCALL_BITMASK;
+    arg1 |= (source_pd << SOURCE_PD_BITSHIFT) & SOURCE_PD_BITMASK;
+
+    let arg2 = dest_pd;
+    let arg3 = source_crd.val();
+    let arg4 = flags;
+    let arg5 = dest_crd.val();
+
+    unsafe {
+        generic_syscall(arg1, arg2, arg3, arg4.val(), arg5)
+            .map(|_x| ())
+            .map_err(|e| e.0)
+    }
+}
+
+/// Errors of [`msr_read`] and [`msr_write`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MsrAccessError {
+    /// The calling PD is not allowed to access MSRs. Only PDs with passthrough
+    /// access, such as the roottask, can do this.
+    NotPermitted,
+    /// The MSR doesn't exist, isn't accessible, or doesn't accept the value.
+    /// The access caused a #GP in the kernel.
+    InvalidMsr,
+    /// Any other error reported by the kernel.
+    Other(HedronError),
+}
+
+impl From<HedronError> for MsrAccessError {
+    fn from(error: HedronError) -> Self {
+        match error.status() {
+            Some(SyscallStatus::BadCap | SyscallStatus::BadHyp) => Self::NotPermitted,
+            Some(SyscallStatus::BadPar) => Self::InvalidMsr,
+            _ => Self::Other(error),
+        }
+    }
+}
+
+/// Reads the model-specific register (MSR) with the given index via the
+/// `pd_ctrl_msr_access` syscall.
+pub fn msr_read(msr: u32) -> Result<u64, MsrAccessError> {
+    pd_ctrl_msr_access(msr, None)
+}
+
+/// Writes the value into the model-specific register (MSR) with the given index via the
+/// `pd_ctrl_msr_access` syscall.
+pub fn msr_write(msr: u32, value: u64) -> Result<(), MsrAccessError> {
+    pd_ctrl_msr_access(msr, Some(value)).map(|_x| ())
+}
+
+/// System call `pd_ctrl_msr_access` reads or writes a model-specific register (MSR) on the
+/// current CPU. If `write_value` is set, the value is written. Otherwise, the MSR is read and
+/// the value is returned.
+fn pd_ctrl_msr_access(msr: u32, write_value: Option<u64>) -> Result<u64, MsrAccessError> {
+    const SYSCALL_BITMASK: u64 = 0xff;
+    const SUB_SYSCALL_BITMASK: u64 = 0x300;
+    const SUB_SYSCALL_BITSHIFT: u64 = 8;
+    const WRITE_BIT: u64 = 1 << 10;
+    const MSR_BITSHIFT: u64 = 12;
+
+    let mut arg1 = 0;
+    arg1 |= SyscallNum::PdCtrl.val() & SYSCALL_BITMASK;
+    arg1 |= (PdCtrlSubSyscall::PdCtrlMsgAccess.val() << SUB_SYSCALL_BITSHIFT) & SUB_SYSCALL_BITMASK;
+    if write_value.is_some() {
+        arg1 |= WRITE_BIT;
+    }
+    arg1 |= (msr as u64) << MSR_BITSHIFT;
+
+    let arg2 = write_value.unwrap_or(0);
+
+    unsafe {
+        generic_syscall(arg1, arg2, 0, 0, 0)
+            .map(|(value, _)| value)
+            .map_err(|e| MsrAccessError::from(e.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The encoding of Hedron: kind in bit 0, inverted host flag in bit 8, device in bit 9,
+    /// guest in bit 10, hypervisor in bit 11, and the hotspot from bit 12 upwards. The
+    /// expected values are literals on purpose, so that they don't depend on the constants
+    /// under test.
+    #[test]
+    fn test_delegate_flags_abi_bits() {
+        assert_eq!(DelegateFlags::default().val(), 0x1);
+        assert_eq!(DelegateFlags::builder().host(false).build().val(), 0x101);
+        assert_eq!(DelegateFlags::builder().device(true).build().val(), 0x201);
+        assert_eq!(DelegateFlags::builder().guest(true).build().val(), 0x401);
+        assert!(DelegateFlags::from_val(0x200).device());
+        assert!(!DelegateFlags::from_val(0x200).guest());
+        assert!(DelegateFlags::from_val(0x400).guest());
+        assert!(!DelegateFlags::from_val(0x400).device());
+        assert!(!DelegateFlags::from_val(0x100).host());
+        assert!(DelegateFlags::from_val(0x800).hypervisor());
+        assert_eq!(
+            DelegateFlags::builder().hypervisor(true).build().val(),
+            0x801
+        );
+        assert_eq!(
+            DelegateFlags::builder().hotspot(0x1337).build().val(),
+            0x1337001
+        );
+        assert_eq!(
+            DelegateFlags::builder()
+                .kind(TypedItemKind::Translate)
+                .build()
+                .val(),
+            0x0
+        );
+    }
+
+    #[test]
+    fn test_delegate_flags_round_trip() {
+        let hotspots = [0, 1, 0x1337, DelegateFlags::MAX_HOTSPOT];
+        let kinds = [TypedItemKind::Translate, TypedItemKind::Delegate];
+        for bits in 0..16 {
+            let host = bits & 1 != 0;
+            let device = bits & 2 != 0;
+            let guest = bits & 4 != 0;
+            let hypervisor = bits & 8 != 0;
+            for hotspot in hotspots {
+                for kind in kinds {
+                    let flags = DelegateFlags::builder()
+                        .kind(kind)
+                        .host(host)
+                        .device(device)
+                        .guest(guest)
+                        .hypervisor(hypervisor)
+                        .hotspot(hotspot)
+                        .build();
+                    let decoded = DelegateFlags::from_val(flags.val());
+                    assert_eq!(decoded.kind(), kind);
+                    assert_eq!(decoded.host(), host);
+                    assert_eq!(decoded.device(), device);
+                    assert_eq!(decoded.guest(), guest);
+                    assert_eq!(decoded.hypervisor(), hypervisor);
+                    assert_eq!(decoded.hotspot(), hotspot);
+                    assert_eq!(decoded, flags);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_delegate_flags_new_matches_builder() {
+        let flags = DelegateFlags::new(true, false, true, true, 42);
+        let expected = DelegateFlags::builder()
+            .guest(true)
+            .hypervisor(true)
+            .hotspot(42)
+            .build();
+        assert_eq!(flags, expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_delegate_flags_hotspot_too_large() {
+        let _ = DelegateFlags::builder().hotspot(DelegateFlags::MAX_HOTSPOT + 1);
+    }
+}
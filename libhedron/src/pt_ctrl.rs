@@ -0,0 +1,51 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the `pt_ctrl` syscall.
+
+use crate::capability::CapSel;
+use crate::error::HedronError;
+use crate::syscall::{encode_arg1, generic_syscall, SyscallNum};
+use crate::NUM_CAP_SEL;
+
+/// Identifier of a portal. The kernel passes it in `rdi` to the local EC, when the portal is
+/// called. This way, one local EC can serve multiple portals and demultiplex the calls.
+pub type PortalId = u64;
+
+/// System call `pt_ctrl` sets the [`PortalId`] of a portal. The capability needs the
+/// [`crate::capability::PTCapPermissions::PT_CTRL`] permission.
+pub fn pt_ctrl(pt_sel: CapSel, id: PortalId) -> Result<(), HedronError> {
+    assert!(
+        pt_sel < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+
+    let arg1 = encode_arg1(SyscallNum::PtCtrl, 0, pt_sel);
+    let arg2 = id;
+
+    unsafe {
+        generic_syscall(arg1, arg2, 0, 0, 0)
+            .map(|_x| ())
+            .map_err(|e| e.0)
+    }
+}
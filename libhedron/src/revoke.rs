@@ -0,0 +1,73 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the `revoke` syscall.
+
+use crate::capability::{CapSel, Crd};
+use crate::error::HedronError;
+use crate::syscall::{encode_arg1, generic_syscall, SyscallNum};
+use crate::NUM_CAP_SEL;
+
+/// System call `revoke` removes capabilities (permissions) recursively from all protection
+/// domains that received them via delegation from the caller. The permission bits of the
+/// [`Crd`] specify which permissions are removed. Memory, port I/O, and object capabilities
+/// are supported.
+///
+/// # Parameters
+/// - `crd` A [`Crd`] that describes the capabilities in the capability space of the caller or
+///   of `remote_pd`.
+/// - `include_self` If set, the capabilities are also removed from the PD itself and not only
+///   from the PDs they were delegated to. This is used to unmap own memory or to give up own
+///   port I/O or object capabilities.
+/// - `remote_pd` If set, the `crd` refers to the capability space of this PD instead of the
+///   caller's one.
+pub fn revoke<Perm, Spec, ObjSpec>(
+    crd: Crd<Perm, Spec, ObjSpec>,
+    include_self: bool,
+    remote_pd: Option<CapSel>,
+) -> Result<(), HedronError> {
+    const FLAG_SELF: u8 = 1 << 0;
+    const FLAG_REMOTE: u8 = 1 << 1;
+
+    let mut flags = 0;
+    if include_self {
+        flags |= FLAG_SELF;
+    }
+    if let Some(pd) = remote_pd {
+        assert!(
+            pd < NUM_CAP_SEL,
+            "maximum cap sel for object capabilities exceeded!"
+        );
+        flags |= FLAG_REMOTE;
+    }
+
+    let arg1 = encode_arg1(SyscallNum::Revoke, flags, 0);
+    let arg2 = crd.val();
+    let arg3 = remote_pd.unwrap_or(0);
+
+    unsafe {
+        generic_syscall(arg1, arg2, arg3, 0, 0)
+            .map(|_x| ())
+            .map_err(|e| e.0)
+    }
+}
@@ -21,19 +21,29 @@ LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
 OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
-//! Important type definitions of Hedron.
-
-#![allow(unused)]
-
-use crate::hedron::capability::CapSel;
-
-pub mod capability;
-pub mod pd_ctrl;
-pub mod syscall;
-
-/// Maximum of 2^26 = 67108864 capability selectors for kernel objects.
-/// Note that this number can be higher for memory capabilities!
-pub const NUM_CAP_SEL: CapSel = 67108864;
-
-/// By convention this is the capability selector of the roottask itself.
-pub const ROOTTASK_CAPSEL: CapSel = 32;
+//! Typings for the `sc_ctrl` syscall.
+
+use crate::capability::CapSel;
+use crate::error::HedronError;
+use crate::syscall::{encode_arg1, generic_syscall, SyscallNum};
+use crate::NUM_CAP_SEL;
+use core::time::Duration;
+
+/// System call `sc_ctrl` returns the CPU time that the given scheduling context consumed
+/// so far. The capability needs the [`crate::capability::SCCapPermissions::SC_CTRL`]
+/// permission.
+pub fn sc_ctrl(sc_sel: CapSel) -> Result<Duration, HedronError> {
+    assert!(
+        sc_sel < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+
+    let arg1 = encode_arg1(SyscallNum::ScCtrl, 0, sc_sel);
+
+    unsafe {
+        generic_syscall(arg1, 0, 0, 0, 0)
+            // the consumed time in microseconds is split into the upper and the lower 32 bits
+            .map(|(hi, lo)| Duration::from_micros((hi << 32) | (lo & 0xffff_ffff)))
+            .map_err(|e| e.0)
+    }
+}
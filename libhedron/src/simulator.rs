@@ -0,0 +1,994 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! In-process simulation of Hedron for host-side tests. Only available with the `simulator`
+//! feature, which replaces the backend of [`crate::syscall::generic_syscall`].
+//!
+//! The simulator models PDs with their object, memory, and port I/O capability spaces, the
+//! delegation tree that `revoke` walks, and semaphores. All syscalls are issued by the
+//! roottask, which owns its PD, EC, and SC at the usual capability selectors. Memory and
+//! port I/O delegations with the hypervisor flag take their source from the hypervisor,
+//! which owns everything. The kernel PD holds the semaphores of all GSIs at
+//! `NUM_CPU + gsi`, as in Hedron, where `NUM_CPU` is the number of CPU slots.
+//!
+//! Each thread has its own simulator, so tests that run in parallel don't interfere. Use
+//! [`reset`] at the start of a test and [`with`] to inspect the state afterwards.
+//!
+//! The simulator doesn't execute code in other ECs. Hence, `call` fails with
+//! [`SyscallStatus::BadFtr`] and a `down` on a semaphore without a counter and without a
+//! timeout panics, because the caller would block forever.
+
+extern crate std;
+
+use crate::capability::{
+    CapSel, CrdKind, ECCapPermissions, MemCapPermissions, PDCapPermissions, PTCapPermissions,
+    PortIOCapPermissions, SCCapPermissions, SMCapPermissions,
+};
+use crate::hip::{Hip, HIP_SIGNATURE};
+use crate::mtd::Mtd;
+use crate::pt_ctrl::PortalId;
+use crate::syscall::{PdCtrlSubSyscall, SyscallBackend, SyscallNum, SyscallStatus};
+use crate::{NUM_CAP_SEL, ROOTTASK_CAPSEL, ROOTTASK_EC_CAPSEL, ROOTTASK_SC_CAPSEL};
+use std::boxed::Box;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::vec::Vec;
+
+std::thread_local! {
+    static SIMULATOR: RefCell<Simulator> = RefCell::new(Simulator::new(SimulatorConfig::default()));
+}
+
+/// Replaces the simulator of the current thread with a fresh one.
+pub fn reset(config: SimulatorConfig) {
+    SIMULATOR.with(|sim| *sim.borrow_mut() = Simulator::new(config));
+}
+
+/// Gives access to the simulator of the current thread.
+pub fn with<R>(f: impl FnOnce(&mut Simulator) -> R) -> R {
+    SIMULATOR.with(|sim| f(&mut sim.borrow_mut()))
+}
+
+/// [`SyscallBackend`] that forwards all syscalls to the simulator of the current thread.
+#[derive(Debug)]
+pub struct SimulatorBackend;
+
+impl SyscallBackend for SimulatorBackend {
+    unsafe fn syscall(arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64) -> (u64, u64, u64) {
+        match with(|sim| sim.syscall(arg1, arg2, arg3, arg4, arg5)) {
+            Ok((out2, out3)) => (SyscallStatus::Success.val(), out2, out3),
+            Err(status) => (status.val(), 0, 0),
+        }
+    }
+}
+
+/// Number of GSIs of each simulated IOAPIC.
+pub const GSIS_PER_IOAPIC: u64 = 24;
+
+/// A memory descriptor of the simulated HIP. See [`crate::hip::HipMem`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SimulatorMemDesc {
+    pub addr: u64,
+    pub size: u64,
+    pub typ: i32,
+    pub aux: u32,
+}
+
+/// Properties of the simulated machine.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SimulatorConfig {
+    /// Number of CPU slots, i.e., `NUM_CPU` of the kernel. Determines the selectors of the
+    /// GSI semaphores in the kernel PD.
+    pub num_cpus: u64,
+    /// Number of CPU slots at the end that are disabled, because the machine has fewer
+    /// CPUs than the kernel supports.
+    pub disabled_cpus: u64,
+    /// Number of GSIs. Determines the number of IOAPICs, see [`GSIS_PER_IOAPIC`].
+    pub num_gsis: u64,
+    /// TSC frequency in kHz.
+    pub tsc_freq_khz: u32,
+    /// Memory descriptors of the HIP.
+    pub memory: &'static [SimulatorMemDesc],
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            num_cpus: 1,
+            disabled_cpus: 0,
+            num_gsis: 24,
+            tsc_freq_khz: 1_000_000,
+            memory: &[
+                SimulatorMemDesc {
+                    addr: 0x10_0000,
+                    size: 0x400_0000,
+                    typ: 1,
+                    aux: 0,
+                },
+                // the hypervisor itself
+                SimulatorMemDesc {
+                    addr: 0x400_0000,
+                    size: 0x100_0000,
+                    typ: -1,
+                    aux: 0,
+                },
+            ],
+        }
+    }
+}
+
+/// Index of a kernel object in the simulator.
+pub type ObjectId = usize;
+
+/// A simulated kernel object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Object {
+    /// Protection domain.
+    Pd,
+    /// Execution context, bound to the PD `pd`.
+    Ec {
+        pd: ObjectId,
+        global: bool,
+        cpu: u16,
+        utcb_addr: u64,
+        evt_base: CapSel,
+    },
+    /// Scheduling context for the EC `ec`.
+    Sc { ec: ObjectId, qpd: u64 },
+    /// Portal that is handled by the EC `ec`.
+    Pt {
+        ec: ObjectId,
+        mtd: Mtd,
+        entry_ip: u64,
+        id: PortalId,
+    },
+    /// Semaphore.
+    Sm { count: u64 },
+}
+
+/// The capability spaces of a PD.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Space {
+    Object,
+    Memory,
+    PortIo,
+}
+
+/// Identifies one capability: owning PD, capability space, and selector, page number, or port.
+type CapKey = (ObjectId, Space, u64);
+
+/// One capability in a capability space.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Cap {
+    /// Referenced kernel object. Only for the object space.
+    obj: Option<ObjectId>,
+    /// Raw permission bits of the [`crate::capability::Crd`].
+    perms: u8,
+    /// The capability that this one was delegated from. `None` for capabilities that were
+    /// created by the syscall or delegated from the hypervisor.
+    parent: Option<CapKey>,
+}
+
+/// A decoded [`crate::capability::Crd`].
+#[derive(Debug, Copy, Clone)]
+struct RawCrd {
+    kind: CrdKind,
+    perms: u8,
+    order: u8,
+    base: u64,
+}
+
+impl RawCrd {
+    fn from_val(val: u64) -> Self {
+        Self {
+            kind: CrdKind::from_lowest_bits(val as u8),
+            perms: ((val >> 2) & 0x1f) as u8,
+            order: ((val >> 7) & 0x1f) as u8,
+            base: val >> 12,
+        }
+    }
+
+    fn space(self) -> Option<Space> {
+        match self.kind {
+            CrdKind::Null => None,
+            CrdKind::Memory => Some(Space::Memory),
+            CrdKind::PortIo => Some(Space::PortIo),
+            CrdKind::Object => Some(Space::Object),
+        }
+    }
+}
+
+/// Simulated state of Hedron. See the module documentation.
+#[derive(Debug)]
+pub struct Simulator {
+    config: SimulatorConfig,
+    objects: Vec<Object>,
+    caps: BTreeMap<CapKey, Cap>,
+    msrs: BTreeMap<u32, u64>,
+    kernel_pd: ObjectId,
+    roottask_pd: ObjectId,
+    suspend_count: u64,
+    hip: &'static Hip,
+}
+
+impl Simulator {
+    /// Creates the initial state: the kernel PD with the GSI semaphores and the roottask with
+    /// its PD, EC, and SC.
+    pub fn new(config: SimulatorConfig) -> Self {
+        let mut sim = Self {
+            config,
+            objects: Vec::new(),
+            caps: BTreeMap::new(),
+            msrs: BTreeMap::new(),
+            kernel_pd: 0,
+            roottask_pd: 0,
+            suspend_count: 0,
+            hip: build_hip(config),
+        };
+        sim.kernel_pd = sim.add_object(Object::Pd);
+        for gsi in 0..config.num_gsis {
+            let sm = sim.add_object(Object::Sm { count: 0 });
+            sim.insert_cap(sim.kernel_pd, config.num_cpus + gsi, sm);
+        }
+        let pd = sim.add_object(Object::Pd);
+        sim.roottask_pd = pd;
+        let ec = sim.add_object(Object::Ec {
+            pd,
+            global: true,
+            cpu: 0,
+            utcb_addr: 0,
+            evt_base: 0,
+        });
+        let sc = sim.add_object(Object::Sc { ec, qpd: 0 });
+        sim.insert_cap(pd, ROOTTASK_CAPSEL, pd);
+        sim.insert_cap(pd, ROOTTASK_EC_CAPSEL, ec);
+        sim.insert_cap(pd, ROOTTASK_SC_CAPSEL, sc);
+        sim
+    }
+
+    /// Returns the configuration of the simulated machine.
+    pub fn config(&self) -> SimulatorConfig {
+        self.config
+    }
+
+    /// Returns the HIP of the simulated machine, as Hedron passes it to the roottask.
+    pub fn hip(&self) -> &'static Hip {
+        self.hip
+    }
+
+    /// Returns the kernel object behind the capability selector of the roottask.
+    pub fn object(&self, sel: CapSel) -> Option<&Object> {
+        let (obj, _) = self.obj_cap(self.roottask_pd, sel)?;
+        self.objects.get(obj)
+    }
+
+    /// Returns the raw permission bits of the object capability at `sel` in the PD that the
+    /// roottask refers to with `pd_sel`.
+    pub fn object_permissions(&self, pd_sel: CapSel, sel: CapSel) -> Option<u8> {
+        let pd = self.pd_of(pd_sel)?;
+        self.obj_cap(pd, sel).map(|(_, perms)| perms)
+    }
+
+    /// Returns the permissions of the memory page in the PD that the roottask refers to with
+    /// `pd_sel`, if the page is mapped.
+    pub fn mem_permissions(&self, pd_sel: CapSel, page: u64) -> Option<MemCapPermissions> {
+        let pd = self.pd_of(pd_sel)?;
+        self.caps
+            .get(&(pd, Space::Memory, page))
+            .map(|cap| MemCapPermissions::from_bits_truncate(cap.perms))
+    }
+
+    /// Returns if the PD that the roottask refers to with `pd_sel` may access the I/O port.
+    pub fn has_port(&self, pd_sel: CapSel, port: u16) -> bool {
+        self.pd_of(pd_sel)
+            .map(|pd| self.caps.contains_key(&(pd, Space::PortIo, port as u64)))
+            .unwrap_or(false)
+    }
+
+    /// Returns the counter of the semaphore behind the capability selector of the roottask.
+    pub fn sm_count(&self, sel: CapSel) -> Option<u64> {
+        match self.object(sel)? {
+            Object::Sm { count } => Some(*count),
+            _ => None,
+        }
+    }
+
+    /// Sets the value of a simulated MSR. Accesses to MSRs without a value fail.
+    pub fn set_msr(&mut self, msr: u32, value: u64) {
+        self.msrs.insert(msr, value);
+    }
+
+    /// Returns the value of a simulated MSR.
+    pub fn msr(&self, msr: u32) -> Option<u64> {
+        self.msrs.get(&msr).copied()
+    }
+
+    /// Returns how often the machine was suspended.
+    pub fn suspend_count(&self) -> u64 {
+        self.suspend_count
+    }
+
+    fn add_object(&mut self, obj: Object) -> ObjectId {
+        self.objects.push(obj);
+        self.objects.len() - 1
+    }
+
+    fn insert_cap(&mut self, pd: ObjectId, sel: CapSel, obj: ObjectId) {
+        // the new capability has all permissions that are defined for the kind of object
+        let perms = match self.objects[obj] {
+            Object::Pd => PDCapPermissions::all().bits(),
+            Object::Ec { .. } => ECCapPermissions::all().bits(),
+            Object::Sc { .. } => SCCapPermissions::all().bits(),
+            Object::Pt { .. } => PTCapPermissions::all().bits(),
+            Object::Sm { .. } => SMCapPermissions::all().bits(),
+        };
+        let cap = Cap {
+            obj: Some(obj),
+            perms,
+            parent: None,
+        };
+        self.caps.insert((pd, Space::Object, sel), cap);
+    }
+
+    fn obj_cap(&self, pd: ObjectId, sel: CapSel) -> Option<(ObjectId, u8)> {
+        let cap = self.caps.get(&(pd, Space::Object, sel))?;
+        Some((cap.obj?, cap.perms))
+    }
+
+    /// Resolves the selector of the roottask to an object of the expected kind that the
+    /// capability grants all the given permissions for.
+    fn lookup(
+        &self,
+        sel: CapSel,
+        perms: u8,
+        matches: fn(&Object) -> bool,
+    ) -> Result<ObjectId, SyscallStatus> {
+        match self.obj_cap(self.roottask_pd, sel) {
+            Some((obj, cap_perms)) if matches(&self.objects[obj]) && cap_perms & perms == perms => {
+                Ok(obj)
+            }
+            _ => Err(SyscallStatus::BadCap),
+        }
+    }
+
+    fn pd_of(&self, pd_sel: CapSel) -> Option<ObjectId> {
+        self.lookup(pd_sel, 0, |o| matches!(o, Object::Pd)).ok()
+    }
+
+    /// Creates the object at the free selector `dest_sel` of the roottask. The owner PD
+    /// must allow the creation of kernel objects.
+    fn create(
+        &mut self,
+        dest_sel: CapSel,
+        owner_pd_sel: CapSel,
+        obj: Object,
+    ) -> Result<(), SyscallStatus> {
+        self.lookup(
+            owner_pd_sel,
+            PDCapPermissions::CREATE_KOBJECTS.bits(),
+            |o| matches!(o, Object::Pd),
+        )?;
+        if self.obj_cap(self.roottask_pd, dest_sel).is_some() {
+            return Err(SyscallStatus::BadCap);
+        }
+        let obj = self.add_object(obj);
+        self.insert_cap(self.roottask_pd, dest_sel, obj);
+        Ok(())
+    }
+
+    /// Handles one syscall with the register ABI of Hedron.
+    fn syscall(
+        &mut self,
+        arg1: u64,
+        arg2: u64,
+        arg3: u64,
+        arg4: u64,
+        arg5: u64,
+    ) -> Result<(u64, u64), SyscallStatus> {
+        let num = arg1 & 0xff;
+        let flags = (arg1 >> 8) & 0xf;
+        let sel = arg1 >> 12;
+        let ok = Ok((0, 0));
+
+        match num {
+            n if n == SyscallNum::Call.val() => {
+                self.lookup(sel, PTCapPermissions::CALL.bits(), |o| {
+                    matches!(o, Object::Pt { .. })
+                })?;
+                // the simulator can't run the handler of the portal
+                Err(SyscallStatus::BadFtr)
+            }
+            n if n == SyscallNum::CreatePd.val() => self.create(sel, arg2, Object::Pd).and(ok),
+            n if n == SyscallNum::CreateEc.val() => {
+                let pd = self.pd_of(arg2).ok_or(SyscallStatus::BadCap)?;
+                let ec = Object::Ec {
+                    pd,
+                    global: flags & 1 != 0,
+                    cpu: (arg3 & 0xfff) as u16,
+                    utcb_addr: arg3 & !0xfff,
+                    evt_base: arg5,
+                };
+                self.create(sel, arg2, ec).and(ok)
+            }
+            n if n == SyscallNum::CreateSc.val() => {
+                let ec = self.lookup(arg3, ECCapPermissions::CREATE_SC.bits(), |o| {
+                    matches!(o, Object::Ec { global: true, .. })
+                })?;
+                self.create(sel, arg2, Object::Sc { ec, qpd: arg4 }).and(ok)
+            }
+            n if n == SyscallNum::CreatePt.val() => {
+                let ec = self.lookup(arg3, ECCapPermissions::CREATE_PT.bits(), |o| {
+                    matches!(o, Object::Ec { global: false, .. })
+                })?;
+                let pt = Object::Pt {
+                    ec,
+                    mtd: Mtd::from_bits_truncate(arg4),
+                    entry_ip: arg5,
+                    id: 0,
+                };
+                self.create(sel, arg2, pt).and(ok)
+            }
+            n if n == SyscallNum::CreateSm.val() => {
+                self.create(sel, arg2, Object::Sm { count: arg3 }).and(ok)
+            }
+            n if n == SyscallNum::Revoke.val() => {
+                let include_self = flags & 1 != 0;
+                let pd = if flags & 2 != 0 {
+                    self.pd_of(arg3).ok_or(SyscallStatus::BadCap)?
+                } else {
+                    self.roottask_pd
+                };
+                self.revoke(pd, RawCrd::from_val(arg2), include_self);
+                ok
+            }
+            n if n == SyscallNum::PdCtrl.val() => {
+                let sub = (arg1 >> 8) & 0x3;
+                if sub == PdCtrlSubSyscall::PdCtrlDelegate.val() {
+                    let flags = crate::pd_ctrl::DelegateFlags::from_val(arg4);
+                    self.delegate(sel, arg2, arg3, arg5, flags.hypervisor())
+                        .and(ok)
+                } else if sub == PdCtrlSubSyscall::PdCtrlMsgAccess.val() {
+                    let msr = (arg1 >> 12) as u32;
+                    let value = self.msrs.get_mut(&msr).ok_or(SyscallStatus::BadPar)?;
+                    if arg1 & (1 << 10) != 0 {
+                        *value = arg2;
+                    }
+                    Ok((*value, 0))
+                } else {
+                    Err(SyscallStatus::BadPar)
+                }
+            }
+            n if n == SyscallNum::EcTrl.val() => {
+                self.lookup(sel, ECCapPermissions::EC_CTRL.bits(), |o| {
+                    matches!(o, Object::Ec { .. })
+                })?;
+                ok
+            }
+            n if n == SyscallNum::ScCtrl.val() => {
+                self.lookup(sel, SCCapPermissions::SC_CTRL.bits(), |o| {
+                    matches!(o, Object::Sc { .. })
+                })?;
+                // no time passes in the simulator
+                ok
+            }
+            n if n == SyscallNum::PtCtrl.val() => {
+                let pt = self.lookup(sel, PTCapPermissions::PT_CTRL.bits(), |o| {
+                    matches!(o, Object::Pt { .. })
+                })?;
+                if let Object::Pt { id, .. } = &mut self.objects[pt] {
+                    *id = arg2;
+                }
+                ok
+            }
+            n if n == SyscallNum::SmCtrl.val() => self.sm_ctrl(sel, flags, (arg2 << 32) | arg3),
+            n if n == SyscallNum::AssignPci.val() => {
+                self.pd_of(sel).ok_or(SyscallStatus::BadCap)?;
+                ok
+            }
+            n if n == SyscallNum::AssignGsi.val() => {
+                self.lookup(sel, 0, |o| matches!(o, Object::Sm { .. }))?;
+                if arg2 == 0 {
+                    // IOAPIC pin
+                    ok
+                } else {
+                    // MSI to the local APIC of the CPU
+                    Ok((0xfee0_0000 | ((arg3 & 0xff) << 12), 0x20 + (sel & 0xff)))
+                }
+            }
+            n if n == SyscallNum::MachineCtrl.val() => {
+                if (arg1 >> 8) & 0x3 == 0 {
+                    self.suspend_count += 1;
+                }
+                ok
+            }
+            _ => Err(SyscallStatus::BadHyp),
+        }
+    }
+
+    fn sm_ctrl(
+        &mut self,
+        sel: CapSel,
+        flags: u64,
+        deadline: u64,
+    ) -> Result<(u64, u64), SyscallStatus> {
+        let down = flags & 1 != 0;
+        let zero = flags & 2 != 0;
+        let perms = if down {
+            SMCapPermissions::DOWN
+        } else {
+            SMCapPermissions::UP
+        };
+        let sm = self.lookup(sel, perms.bits(), |o| matches!(o, Object::Sm { .. }))?;
+        let count = match &mut self.objects[sm] {
+            Object::Sm { count } => count,
+            _ => unreachable!(),
+        };
+        if !down {
+            *count += 1;
+        } else if *count > 0 {
+            *count = if zero { 0 } else { *count - 1 };
+        } else if deadline != 0 {
+            // no other EC can perform an `UP`, the deadline passes for sure
+            return Err(SyscallStatus::Timeout);
+        } else {
+            panic!("down on semaphore {sel} without timeout would block forever");
+        }
+        Ok((0, 0))
+    }
+
+    /// Delegates from the PD `src_pd_sel` (or from the hypervisor) to `dest_pd_sel`. Both
+    /// ranges have the size of the smaller one.
+    fn delegate(
+        &mut self,
+        src_pd_sel: CapSel,
+        dest_pd_sel: CapSel,
+        src_crd: u64,
+        dest_crd: u64,
+        from_hypervisor: bool,
+    ) -> Result<(), SyscallStatus> {
+        let src_pd = self.pd_of(src_pd_sel).ok_or(SyscallStatus::BadCap)?;
+        let dest_pd = self.pd_of(dest_pd_sel).ok_or(SyscallStatus::BadCap)?;
+        let src_crd = RawCrd::from_val(src_crd);
+        let dest_crd = RawCrd::from_val(dest_crd);
+        let space = match (src_crd.space(), dest_crd.space()) {
+            (Some(src), Some(dest)) if src == dest => src,
+            (None, _) | (_, None) => return Ok(()),
+            _ => return Err(SyscallStatus::BadPar),
+        };
+        // the hypervisor flag is only respected for the roottask
+        let from_hypervisor = from_hypervisor && src_pd == self.roottask_pd;
+        let count = 1_u64 << src_crd.order.min(dest_crd.order);
+        for i in 0..count {
+            let src_key = if from_hypervisor {
+                (self.kernel_pd, space, src_crd.base + i)
+            } else {
+                (src_pd, space, src_crd.base + i)
+            };
+            let src_cap = match self.caps.get(&src_key) {
+                Some(cap) => Some(*cap),
+                // the hypervisor owns all memory and all I/O ports
+                None if from_hypervisor && space == Space::Memory => Some(Cap {
+                    obj: None,
+                    perms: MemCapPermissions::all().bits(),
+                    parent: None,
+                }),
+                None if from_hypervisor && space == Space::PortIo => Some(Cap {
+                    obj: None,
+                    perms: PortIOCapPermissions::all().bits(),
+                    parent: None,
+                }),
+                None => None,
+            };
+            let src_cap = match src_cap {
+                Some(cap) => cap,
+                None => continue,
+            };
+            let perms = src_cap.perms & src_crd.perms;
+            if perms == 0 {
+                continue;
+            }
+            let cap = Cap {
+                obj: src_cap.obj,
+                perms,
+                parent: (!from_hypervisor).then_some(src_key),
+            };
+            self.caps.insert((dest_pd, space, dest_crd.base + i), cap);
+        }
+        Ok(())
+    }
+
+    /// Removes the permissions of the CRD from all capabilities that were derived from the
+    /// ones in the range and, if `include_self` is set, from the range itself.
+    fn revoke(&mut self, pd: ObjectId, crd: RawCrd, include_self: bool) {
+        let space = match crd.space() {
+            Some(space) => space,
+            None => return,
+        };
+        for i in 0..(1_u64 << crd.order) {
+            self.revoke_cap((pd, space, crd.base + i), crd.perms, include_self);
+        }
+    }
+
+    fn revoke_cap(&mut self, key: CapKey, perms: u8, include_self: bool) {
+        let children = self
+            .caps
+            .iter()
+            .filter(|(_, cap)| cap.parent == Some(key))
+            .map(|(child, _)| *child)
+            .collect::<Vec<_>>();
+        for child in children {
+            self.revoke_cap(child, perms, true);
+        }
+        if include_self {
+            if let Some(cap) = self.caps.get_mut(&key) {
+                cap.perms &= !perms;
+                if cap.perms == 0 {
+                    self.caps.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+/// Builds a valid HIP for the configuration. The memory is leaked, as a real HIP lives for
+/// the lifetime of the roottask.
+///
+/// The layout is taken from `hip.hpp` of Hedron and deliberately doesn't use the
+/// definitions of [`Hip`], so that the tests catch a parser that doesn't match Hedron: the
+/// header is followed by the CPU descriptors, the IOAPIC descriptors, and the memory
+/// descriptors, which the compiler aligns to 8 bytes. There is one IOAPIC for every
+/// [`GSIS_PER_IOAPIC`] GSIs.
+fn build_hip(config: SimulatorConfig) -> &'static Hip {
+    // offsets of the header fields in `hip.hpp`
+    const SIGNATURE: usize = 0x0;
+    const CHECKSUM: usize = 0x4;
+    const LENGTH: usize = 0x6;
+    const CPU_OFFS: usize = 0x8;
+    const CPU_SIZE: usize = 0xa;
+    const IOAPIC_OFFS: usize = 0xc;
+    const IOAPIC_SIZE: usize = 0xe;
+    const MEM_OFFS: usize = 0x10;
+    const MEM_SIZE: usize = 0x12;
+    const SEL_NUM: usize = 0x1c;
+    const SEL_EXC: usize = 0x20;
+    const SEL_VMI: usize = 0x24;
+    const SEL_GSI: usize = 0x28;
+    const CFG_PAGE: usize = 0x2c;
+    const CFG_UTCB: usize = 0x30;
+    const FREQ_TSC: usize = 0x34;
+    const HEADER_SIZE: usize = 0x3c;
+    // sizes of `Hip_cpu`, `Hip_ioapic`, and `Hip_mem`
+    const CPU_DESC_SIZE: usize = 8;
+    const IOAPIC_DESC_SIZE: usize = 16;
+    const MEM_DESC_SIZE: usize = 24;
+
+    let num_ioapics = (config.num_gsis + GSIS_PER_IOAPIC - 1) / GSIS_PER_IOAPIC;
+    let cpu_offset = HEADER_SIZE;
+    let ioapic_offset = cpu_offset + config.num_cpus as usize * CPU_DESC_SIZE;
+    let mem_offset = (ioapic_offset + num_ioapics as usize * IOAPIC_DESC_SIZE + 7) & !7;
+    let length = mem_offset + config.memory.len() * MEM_DESC_SIZE;
+    // u64 words guarantee the alignment of the HIP
+    let mut words = std::vec![0_u64; (length + 7) / 8];
+    let bytes = unsafe { core::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, length) };
+
+    let mut write =
+        |offset: usize, val: &[u8]| bytes[offset..offset + val.len()].copy_from_slice(val);
+    write(SIGNATURE, &HIP_SIGNATURE.to_le_bytes());
+    write(LENGTH, &(length as u16).to_le_bytes());
+    write(CPU_OFFS, &(cpu_offset as u16).to_le_bytes());
+    write(CPU_SIZE, &(CPU_DESC_SIZE as u16).to_le_bytes());
+    write(IOAPIC_OFFS, &(ioapic_offset as u16).to_le_bytes());
+    write(IOAPIC_SIZE, &(IOAPIC_DESC_SIZE as u16).to_le_bytes());
+    write(MEM_OFFS, &(mem_offset as u16).to_le_bytes());
+    write(MEM_SIZE, &(MEM_DESC_SIZE as u16).to_le_bytes());
+    write(SEL_NUM, &(NUM_CAP_SEL as u32).to_le_bytes());
+    write(SEL_EXC, &(ROOTTASK_CAPSEL as u32).to_le_bytes());
+    write(SEL_VMI, &0_u32.to_le_bytes());
+    write(SEL_GSI, &(config.num_gsis as u32).to_le_bytes());
+    // 4 KiB pages and UTCBs
+    write(CFG_PAGE, &(1_u32 << 12).to_le_bytes());
+    write(CFG_UTCB, &(1_u32 << 12).to_le_bytes());
+    write(FREQ_TSC, &config.tsc_freq_khz.to_le_bytes());
+    let enabled_cpus = config.num_cpus - config.disabled_cpus;
+    for cpu in 0..config.num_cpus as usize {
+        // flags: enabled; thread, core, package, acpi_id
+        let offset = cpu_offset + cpu * CPU_DESC_SIZE;
+        let enabled = (cpu < enabled_cpus as usize) as u8;
+        write(offset, &[enabled, 0, cpu as u8, 0, cpu as u8]);
+    }
+    for ioapic in 0..num_ioapics {
+        // id, version, gsi_base, and the MMIO base of the QEMU IOAPIC and its successors
+        let offset = ioapic_offset + ioapic as usize * IOAPIC_DESC_SIZE;
+        write(offset, &(ioapic as u32).to_le_bytes());
+        write(offset + 4, &0x20_u32.to_le_bytes());
+        write(
+            offset + 8,
+            &((ioapic * GSIS_PER_IOAPIC) as u32).to_le_bytes(),
+        );
+        write(
+            offset + 12,
+            &(0xfec0_0000_u32 + ioapic as u32 * 0x1000).to_le_bytes(),
+        );
+    }
+    for (i, mem) in config.memory.iter().enumerate() {
+        let offset = mem_offset + i * MEM_DESC_SIZE;
+        write(offset, &mem.addr.to_le_bytes());
+        write(offset + 8, &mem.size.to_le_bytes());
+        write(offset + 16, &mem.typ.to_le_bytes());
+        write(offset + 20, &mem.aux.to_le_bytes());
+    }
+
+    // the sum of all 16-bit words must be zero
+    let sum = bytes.chunks(2).fold(0_u16, |acc, w| {
+        acc.wrapping_add(u16::from_le_bytes([w[0], w[1]]))
+    });
+    bytes[CHECKSUM..CHECKSUM + 2].copy_from_slice(&0_u16.wrapping_sub(sum).to_le_bytes());
+
+    let ptr = Box::leak(words.into_boxed_slice()).as_ptr() as *const u8;
+    unsafe { Hip::from_ptr(ptr) }.expect("simulated HIP must be valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::{CrdMem, CrdNull, CrdObjSM, CrdPortIO};
+    use crate::create_pd::create_pd;
+    use crate::create_sm::create_sm;
+    use crate::delegate::{delegate_ports, map_phys_range};
+    use crate::error::HedronError;
+    use crate::pd_ctrl::{msr_read, msr_write, pd_ctrl_delegate, DelegateFlags, MsrAccessError};
+    use crate::revoke::revoke;
+    use crate::sm_ctrl::{sm_ctrl_down, sm_ctrl_up};
+
+    const CHILD_PD: CapSel = 100;
+
+    fn hypervisor_flags() -> DelegateFlags {
+        DelegateFlags::builder().hypervisor(true).build()
+    }
+
+    #[test]
+    fn test_hip() {
+        reset(SimulatorConfig {
+            num_cpus: 4,
+            ..SimulatorConfig::default()
+        });
+        let hip = with(|sim| sim.hip());
+        assert_eq!(hip.num_cpus(), 4);
+        assert_eq!(hip.num_exc_sel(), ROOTTASK_CAPSEL);
+        assert_eq!(hip.mem_descs().count(), 2);
+        assert_eq!(hip.boot_modules().count(), 0);
+    }
+
+    #[test]
+    fn test_hip_ioapics() {
+        reset(SimulatorConfig {
+            num_cpus: 3,
+            num_gsis: 48,
+            ..SimulatorConfig::default()
+        });
+        let hip = with(|sim| sim.hip());
+        // the IOAPIC descriptors are neither CPUs nor memory
+        assert_eq!(hip.cpus().count(), 3);
+        assert_eq!(hip.mem_descs().count(), 2);
+        let gsi_bases = hip.ioapics().map(|ioapic| ioapic.gsi_base());
+        assert!(gsi_bases.eq([0, 24]));
+        assert_eq!(hip.num_gsi_sel(), 48);
+        assert_eq!(hip.tsc_freq_khz(), 1_000_000);
+    }
+
+    #[test]
+    fn test_hip_boot_modules() {
+        reset(SimulatorConfig {
+            memory: &[
+                SimulatorMemDesc {
+                    addr: 0x10_0000,
+                    size: 0x400_0000,
+                    typ: 1,
+                    aux: 0,
+                },
+                SimulatorMemDesc {
+                    addr: 0x200_0000,
+                    size: 0x2_0000,
+                    typ: -2,
+                    aux: 0x1000,
+                },
+                SimulatorMemDesc {
+                    addr: 0x300_0000,
+                    size: 0x1234,
+                    typ: -2,
+                    aux: 0x1100,
+                },
+            ],
+            ..SimulatorConfig::default()
+        });
+        let hip = with(|sim| sim.hip());
+        let modules = hip
+            .boot_modules()
+            .map(|m| (m.addr(), m.size(), m.aux()))
+            .collect::<std::vec::Vec<_>>();
+        assert_eq!(
+            modules,
+            [(0x200_0000, 0x2_0000, 0x1000), (0x300_0000, 0x1234, 0x1100)]
+        );
+    }
+
+    #[test]
+    fn test_port_delegation_and_revoke() {
+        reset(SimulatorConfig::default());
+        create_pd(CHILD_PD, ROOTTASK_CAPSEL, CrdNull::new()).unwrap();
+        delegate_ports(0x3f8..=0x3ff, ROOTTASK_CAPSEL, hypervisor_flags()).unwrap();
+        pd_ctrl_delegate(
+            ROOTTASK_CAPSEL,
+            CHILD_PD,
+            CrdPortIO::new(0x3f8, 3),
+            CrdPortIO::new(0x3f8, 3),
+            DelegateFlags::default(),
+        )
+        .unwrap();
+        with(|sim| {
+            assert!(sim.has_port(ROOTTASK_CAPSEL, 0x3ff));
+            assert!(sim.has_port(CHILD_PD, 0x3f8));
+            assert!(!sim.has_port(CHILD_PD, 0x400));
+        });
+
+        // revoking from the children keeps the own capability
+        revoke(CrdPortIO::new(0x3f8, 3), false, None).unwrap();
+        with(|sim| {
+            assert!(sim.has_port(ROOTTASK_CAPSEL, 0x3f8));
+            assert!(!sim.has_port(CHILD_PD, 0x3f8));
+        });
+        revoke(CrdPortIO::new(0x3f8, 3), true, None).unwrap();
+        with(|sim| assert!(!sim.has_port(ROOTTASK_CAPSEL, 0x3f8)));
+    }
+
+    #[test]
+    fn test_memory_delegation_reduces_permissions() {
+        reset(SimulatorConfig::default());
+        create_pd(CHILD_PD, ROOTTASK_CAPSEL, CrdNull::new()).unwrap();
+        map_phys_range(
+            0x1000,
+            0x2000_0000,
+            0x3000,
+            MemCapPermissions::all(),
+            hypervisor_flags(),
+        )
+        .unwrap();
+        pd_ctrl_delegate(
+            ROOTTASK_CAPSEL,
+            CHILD_PD,
+            CrdMem::new(0x2000_0000 / 4096, 1, MemCapPermissions::READ),
+            CrdMem::new(0x10, 1, MemCapPermissions::READ),
+            DelegateFlags::default(),
+        )
+        .unwrap();
+        with(|sim| {
+            let page = 0x2000_0000 / 4096;
+            assert_eq!(
+                sim.mem_permissions(ROOTTASK_CAPSEL, page + 2),
+                Some(MemCapPermissions::all())
+            );
+            assert_eq!(sim.mem_permissions(ROOTTASK_CAPSEL, page + 3), None);
+            assert_eq!(
+                sim.mem_permissions(CHILD_PD, 0x11),
+                Some(MemCapPermissions::READ)
+            );
+        });
+
+        // revoking the own mapping also revokes the derived one
+        revoke(
+            CrdMem::new(0x2000_0000 / 4096, 0, MemCapPermissions::all()),
+            true,
+            None,
+        )
+        .unwrap();
+        with(|sim| {
+            assert_eq!(sim.mem_permissions(CHILD_PD, 0x10), None);
+            assert_eq!(
+                sim.mem_permissions(CHILD_PD, 0x11),
+                Some(MemCapPermissions::READ)
+            );
+        });
+    }
+
+    #[test]
+    fn test_semaphores() {
+        reset(SimulatorConfig::default());
+        create_sm(200, ROOTTASK_CAPSEL, 2).unwrap();
+        assert_eq!(
+            create_sm(200, ROOTTASK_CAPSEL, 0),
+            Err(HedronError::Syscall(SyscallStatus::BadCap))
+        );
+        sm_ctrl_up(200).unwrap();
+        sm_ctrl_down(200, false, None).unwrap();
+        with(|sim| assert_eq!(sim.sm_count(200), Some(2)));
+        sm_ctrl_down(200, true, None).unwrap();
+        assert_eq!(
+            sm_ctrl_down(200, false, Some(1)),
+            Err(HedronError::Syscall(SyscallStatus::Timeout))
+        );
+
+        // an object capability without the DOWN permission
+        pd_ctrl_delegate(
+            ROOTTASK_CAPSEL,
+            ROOTTASK_CAPSEL,
+            CrdObjSM::new(200, 0, SMCapPermissions::UP),
+            CrdObjSM::new(201, 0, SMCapPermissions::UP),
+            DelegateFlags::default(),
+        )
+        .unwrap();
+        sm_ctrl_up(201).unwrap();
+        assert_eq!(
+            sm_ctrl_down(201, false, None),
+            Err(HedronError::Syscall(SyscallStatus::BadCap))
+        );
+        with(|sim| assert_eq!(sim.sm_count(200), Some(1)));
+    }
+
+    #[test]
+    fn test_gsi_semaphore_from_kernel_pd() {
+        reset(SimulatorConfig::default());
+        let hip = with(|sim| sim.hip());
+        let kernel_sel = crate::assign_gsi::gsi_sm_kernel_sel(hip, 4);
+        pd_ctrl_delegate(
+            ROOTTASK_CAPSEL,
+            ROOTTASK_CAPSEL,
+            CrdObjSM::new(kernel_sel, 0, SMCapPermissions::all()),
+            CrdObjSM::new(300, 0, SMCapPermissions::all()),
+            hypervisor_flags(),
+        )
+        .unwrap();
+        with(|sim| assert_eq!(sim.sm_count(300), Some(0)));
+    }
+
+    #[test]
+    fn test_gsi_semaphore_with_disabled_cpus() {
+        reset(SimulatorConfig {
+            num_cpus: 4,
+            disabled_cpus: 2,
+            ..SimulatorConfig::default()
+        });
+        let hip = with(|sim| sim.hip());
+        assert_eq!(hip.num_cpus(), 2);
+        assert_eq!(hip.num_cpu_slots(), 4);
+        // the kernel counts all slots
+        let kernel_sel = crate::assign_gsi::gsi_sm_kernel_sel(hip, 4);
+        assert_eq!(kernel_sel, 8);
+        pd_ctrl_delegate(
+            ROOTTASK_CAPSEL,
+            ROOTTASK_CAPSEL,
+            CrdObjSM::new(kernel_sel, 0, SMCapPermissions::all()),
+            CrdObjSM::new(300, 0, SMCapPermissions::all()),
+            hypervisor_flags(),
+        )
+        .unwrap();
+        with(|sim| assert_eq!(sim.sm_count(300), Some(0)));
+    }
+
+    #[test]
+    fn test_msr_access() {
+        reset(SimulatorConfig::default());
+        assert_eq!(msr_read(0x1b), Err(MsrAccessError::InvalidMsr));
+        with(|sim| sim.set_msr(0x1b, 0xfee0_0900));
+        msr_write(0x1b, 0xfee0_0800).unwrap();
+        assert_eq!(msr_read(0x1b), Ok(0xfee0_0800));
+    }
+}
@@ -0,0 +1,96 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the `sm_ctrl` syscall.
+
+use crate::capability::CapSel;
+use crate::error::HedronError;
+use crate::syscall::{encode_arg1, generic_syscall, SyscallNum};
+use crate::NUM_CAP_SEL;
+
+/// Sub-operations of the `sm_ctrl` syscall.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SmCtrlOp {
+    /// Increments the counter or wakes up a blocked EC.
+    Up = 0,
+    /// Decrements the counter or blocks the calling EC, if the counter is zero.
+    Down = 1,
+}
+
+impl SmCtrlOp {
+    /// Returns the raw unsigned integer value.
+    pub fn val(self) -> u8 {
+        self as u8
+    }
+}
+
+/// System call `sm_ctrl` with the `UP` operation. Increments the counter of the semaphore
+/// or wakes up an EC that is blocked on it. The capability needs the
+/// [`crate::capability::SMCapPermissions::UP`] permission.
+pub fn sm_ctrl_up(sm_sel: CapSel) -> Result<(), HedronError> {
+    sm_ctrl(sm_sel, SmCtrlOp::Up, false, 0)
+}
+
+/// System call `sm_ctrl` with the `DOWN` operation. Decrements the counter of the semaphore.
+/// If the counter is zero, the calling EC blocks until another EC performs an `UP` operation
+/// or until the deadline is reached. The capability needs the
+/// [`crate::capability::SMCapPermissions::DOWN`] permission.
+///
+/// # Parameters
+/// - `sm_sel` Capability selector of the semaphore.
+/// - `zero` If set, the counter is set to zero instead of being decremented. This consumes all
+///   pending `UP` operations at once, which is useful for interrupt semaphores.
+/// - `deadline` Absolute TSC value after which the operation is aborted with
+///   [`crate::syscall::SyscallStatus::Timeout`]. `None` blocks without a timeout.
+pub fn sm_ctrl_down(sm_sel: CapSel, zero: bool, deadline: Option<u64>) -> Result<(), HedronError> {
+    // The kernel interprets a deadline of zero as "no timeout". Map a zero deadline to
+    // the earliest possible one, so that the caller doesn't block forever by accident.
+    let deadline = deadline.map(|d| d.max(1)).unwrap_or(0);
+    sm_ctrl(sm_sel, SmCtrlOp::Down, zero, deadline)
+}
+
+/// Generic `sm_ctrl` syscall. See [`sm_ctrl_up`] and [`sm_ctrl_down`].
+fn sm_ctrl(sm_sel: CapSel, op: SmCtrlOp, zero: bool, deadline: u64) -> Result<(), HedronError> {
+    assert!(
+        sm_sel < NUM_CAP_SEL,
+        "maximum cap sel for object capabilities exceeded!"
+    );
+    const FLAG_ZERO: u8 = 1 << 1;
+
+    let mut flags = op.val();
+    if zero {
+        flags |= FLAG_ZERO;
+    }
+
+    let arg1 = encode_arg1(SyscallNum::SmCtrl, flags, sm_sel);
+    // the TSC deadline is split into the upper and the lower 32 bits
+    let arg2 = deadline >> 32;
+    let arg3 = deadline & 0xffff_ffff;
+
+    unsafe {
+        generic_syscall(arg1, arg2, arg3, 0, 0)
+            .map(|_x| ())
+            .map_err(|e| e.0)
+    }
+}
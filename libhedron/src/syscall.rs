@@ -0,0 +1,235 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Generic typings for Hedron syscalls.
+
+use crate::capability::CapSel;
+use crate::error::HedronError;
+
+/// Does a NOVA/Hedron syscall with 5 arguments.
+/// On success, the "out2"- and "out3"-values are returned.
+/// On failure, the error code ("out1") is returned
+/// together with "out2". Unknown error codes are reported as
+/// [`HedronError::UnknownStatus`].
+///
+/// The syscall is performed by the [`ActiveBackend`].
+///
+/// # Safety
+/// The arguments must form a valid syscall. Hedron may change the state of the caller,
+/// for example its address space or the content of its UTCB.
+pub unsafe fn generic_syscall(
+    arg1: u64,
+    arg2: u64,
+    arg3: u64,
+    arg4: u64,
+    arg5: u64,
+) -> Result<(u64, u64), (HedronError, u64)> {
+    let (status, out2, out3) = ActiveBackend::syscall(arg1, arg2, arg3, arg4, arg5);
+    match SyscallStatus::try_from(status) {
+        Ok(SyscallStatus::Success) => Ok((out2, out3)),
+        Ok(status) => Err((HedronError::Syscall(status), out2)),
+        Err(e) => Err((e, out2)),
+    }
+}
+
+/// Mechanism that performs the syscalls of all wrappers in this crate. The backend is selected
+/// at compile time, see [`ActiveBackend`].
+pub trait SyscallBackend {
+    /// Performs a syscall with 5 arguments and returns the raw values of "out1" (the status),
+    /// "out2", and "out3". See [`generic_syscall`].
+    ///
+    /// # Safety
+    /// See [`generic_syscall`].
+    unsafe fn syscall(arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64) -> (u64, u64, u64);
+}
+
+/// Backend that performs real syscalls with the `syscall` instruction.
+#[derive(Debug)]
+pub struct AsmBackend;
+
+impl SyscallBackend for AsmBackend {
+    unsafe fn syscall(arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64) -> (u64, u64, u64) {
+        let out1: u64;
+        let out2;
+        let out3;
+        core::arch::asm!(
+            // there is no need to write "mov"-instructions, see below
+            "syscall",
+            // from 'in("rax")' the compiler will
+            // generate corresponding 'mov'-instructions
+            in("rdi") arg1,
+            in("rsi") arg2,
+            inlateout("rdx") arg3 => out3,
+            in("rax") arg4,
+            in("r8") arg5,
+            lateout("rdi") out1,
+            lateout("rsi") out2,
+            // mark as clobbered
+            // https://doc.rust-lang.org/beta/unstable-book/library-features/asm.html
+            // NOVA/Hedron spec lists all registers that may be altered
+            lateout("r11") _,
+            lateout("rcx") _,
+            // Memory Clobber not necessary, because this is the default in Rust
+            options(nostack) // probably no effect, but strictly speaking correct
+        );
+        (out1, out2, out3)
+    }
+}
+
+/// The backend of [`generic_syscall`]: [`AsmBackend`] by default.
+#[cfg(not(feature = "simulator"))]
+pub type ActiveBackend = AsmBackend;
+
+/// The backend of [`generic_syscall`]: the in-process simulator of the `simulator` feature.
+#[cfg(feature = "simulator")]
+pub type ActiveBackend = crate::simulator::SimulatorBackend;
+
+#[derive(Debug, Copy, Clone)]
+#[repr(u64)]
+pub enum SyscallNum {
+    Call = 0,
+    Reply = 1,
+    CreatePd = 2,
+    CreateEc = 3,
+    CreateSc = 4,
+    CreatePt = 5,
+    CreateSm = 6,
+    Revoke = 7,
+    PdCtrl = 8,
+    EcTrl = 9,
+    ScCtrl = 10,
+    PtCtrl = 11,
+    SmCtrl = 12,
+    AssignPci = 13,
+    AssignGsi = 14,
+    MachineCtrl = 15,
+}
+
+impl SyscallNum {
+    pub fn val(self) -> u64 {
+        self as u64
+    }
+}
+
+/// Encodes the first argument of most syscalls. It carries the syscall number in bits 0..=7,
+/// syscall-specific flags in bits 8..=11, and a capability selector in bits 12..=63.
+pub fn encode_arg1(num: SyscallNum, flags: u8, sel: CapSel) -> u64 {
+    const SYSCALL_BITMASK: u64 = 0xff;
+    const FLAGS_BITMASK: u64 = 0xf00;
+    const FLAGS_BITSHIFT: u64 = 8;
+    const SEL_BITMASK: u64 = !0xfff;
+    const SEL_BITSHIFT: u64 = 12;
+
+    let mut arg1 = 0;
+    arg1 |= num.val() & SYSCALL_BITMASK;
+    arg1 |= ((flags as u64) << FLAGS_BITSHIFT) & FLAGS_BITMASK;
+    arg1 |= (sel << SEL_BITSHIFT) & SEL_BITMASK;
+    arg1
+}
+
+#[derive(Debug, Copy, Clone)]
+#[repr(u64)]
+pub enum PdCtrlSubSyscall {
+    PdCtrlDelegate = 2,
+    PdCtrlMsgAccess = 3,
+}
+
+impl PdCtrlSubSyscall {
+    pub fn val(self) -> u64 {
+        self as u64
+    }
+}
+
+/// Possible return values from the syscall.
+/// All except the 0 value are error codes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u64)]
+pub enum SyscallStatus {
+    /// The operation completed successfully
+    Success = 0,
+    /// The operation timed out
+    Timeout = 1,
+    /// The operation was aborted
+    Abort = 2,
+    /// An invalid hypercall was called
+    BadHyp = 3,
+    /// A hypercall referred to an empty or otherwise invalid capability
+    BadCap = 4,
+    /// A hypercall used invalid parameters
+    BadPar = 5,
+    /// An invalid feature was requested
+    BadFtr = 6,
+    /// A portal capability was used on the wrong CPU
+    BadCpu = 7,
+    /// An invalid device ID was passed
+    BadDev = 8,
+}
+
+impl TryFrom<u64> for SyscallStatus {
+    type Error = HedronError;
+
+    /// Decodes the status with respect to [`Self::SYSCALL_STATUS_BITMASK`]. Fails with
+    /// [`HedronError::UnknownStatus`] for codes that this crate doesn't know.
+    fn try_from(val: u64) -> Result<Self, Self::Error> {
+        let status = match val & Self::SYSCALL_STATUS_BITMASK {
+            0 => Self::Success,
+            1 => Self::Timeout,
+            2 => Self::Abort,
+            3 => Self::BadHyp,
+            4 => Self::BadCap,
+            5 => Self::BadPar,
+            6 => Self::BadFtr,
+            7 => Self::BadCpu,
+            8 => Self::BadDev,
+            _ => return Err(HedronError::UnknownStatus(val)),
+        };
+        Ok(status)
+    }
+}
+
+impl SyscallStatus {
+    /// Only the lowest 8 bits are used to encode the status.
+    const SYSCALL_STATUS_BITMASK: u64 = 0xff;
+
+    pub fn val(self) -> u64 {
+        self as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syscall_status_try_from() {
+        assert_eq!(SyscallStatus::try_from(0), Ok(SyscallStatus::Success));
+        assert_eq!(SyscallStatus::try_from(8), Ok(SyscallStatus::BadDev));
+        // only the lowest 8 bits carry the status
+        assert_eq!(SyscallStatus::try_from(0x1_04), Ok(SyscallStatus::BadCap));
+        // codes of newer Hedron versions are preserved
+        assert_eq!(
+            SyscallStatus::try_from(9),
+            Err(HedronError::UnknownStatus(9))
+        );
+    }
+}
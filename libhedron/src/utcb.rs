@@ -0,0 +1,384 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Typings for the User Thread Control Block (UTCB).
+//!
+//! Each EC has a UTCB. It is a page that is shared between the EC and the kernel and
+//! carries the payload of IPC messages. A message consists of untyped items (plain
+//! words that are copied) and typed items (capability delegations). Untyped items
+//! start at the beginning of the data area and grow upwards. Typed items start at
+//! the end of the UTCB and grow downwards.
+//!
+//! When the kernel calls a portal for an exception or the startup of a global EC, the data
+//! area carries the architectural state of the EC instead. See [`ExceptionState`].
+
+use crate::capability::{Crd, CrdKind};
+use crate::mtd::Mtd;
+use crate::pd_ctrl::DelegateFlags;
+use core::fmt::{Debug, Formatter};
+use core::mem::size_of;
+
+/// Size of a UTCB in bytes. Hedron only supports UTCBs with the size of one page.
+pub const UTCB_SIZE: usize = 4096;
+
+/// Number of words in the data area of the UTCB, i.e., after the [`UtcbHead`].
+pub const UTCB_DATA_WORDS: usize = (UTCB_SIZE - size_of::<UtcbHead>()) / size_of::<u64>();
+
+/// Errors when a message doesn't fit into the UTCB.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UtcbError {
+    /// Untyped and typed items together exceed the data area of the UTCB.
+    MessageTooLarge,
+}
+
+/// Header of the UTCB.
+#[derive(Debug)]
+#[repr(C)]
+pub struct UtcbHead {
+    /// Bits 0..=15: number of untyped items, bits 16..=31: number of typed items.
+    items: u64,
+    /// CRD for the translation receive window.
+    xlt: u64,
+    /// CRD for the delegation receive window.
+    del: u64,
+    /// Thread-local storage word. Not interpreted by the kernel.
+    tls: u64,
+}
+
+/// A typed item inside the UTCB. It describes a capability delegation of an IPC
+/// message. The memory layout is the one of the kernel.
+#[derive(Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub struct TypedItem {
+    /// Raw [`Crd`] that describes the capabilities.
+    crd: u64,
+    /// Raw [`DelegateFlags`] of the delegation.
+    flags: u64,
+}
+
+impl TypedItem {
+    /// Creates a new typed item that delegates the capabilities of the [`Crd`].
+    pub fn new<Perm, Spec, ObjSpec>(crd: Crd<Perm, Spec, ObjSpec>, flags: DelegateFlags) -> Self {
+        Self {
+            crd: crd.val(),
+            flags: flags.val(),
+        }
+    }
+
+    /// Returns the raw value of the [`Crd`].
+    pub fn crd_val(self) -> u64 {
+        self.crd
+    }
+
+    /// Returns the [`CrdKind`] of the [`Crd`].
+    pub fn crd_kind(self) -> CrdKind {
+        CrdKind::from_lowest_bits(self.crd as u8)
+    }
+
+    /// Returns the raw value of the [`DelegateFlags`].
+    pub fn flags_val(self) -> u64 {
+        self.flags
+    }
+
+    /// Returns the decoded [`DelegateFlags`].
+    pub fn flags(self) -> DelegateFlags {
+        DelegateFlags::from_val(self.flags)
+    }
+}
+
+impl Debug for TypedItem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TypedItem")
+            // trick: print as pointer => print as hex
+            .field("crd", &(self.crd as *const u8))
+            .field("crd_kind", &self.crd_kind())
+            .field("flags", &self.flags())
+            .finish()
+    }
+}
+
+/// The architectural state of an EC in the data area of the UTCB during the handling of an
+/// exception or the startup of a global EC. The kernel only transfers the parts that the
+/// [`Mtd`] of the portal selects. During the reply, the handler selects in [`Self::mtd`] which
+/// parts are written back. The layout is the one of the kernel; the fields after the control
+/// registers are not modelled here.
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct ExceptionState {
+    /// Raw [`Mtd`]. Set by the kernel on portal entry and by the handler for the reply.
+    pub mtd: u64,
+    pub inst_len: u64,
+    pub rip: u64,
+    pub rflags: u64,
+    pub intr_state: u32,
+    pub actv_state: u32,
+    pub inj_info: u32,
+    pub inj_error: u32,
+    pub rax: u64,
+    pub rcx: u64,
+    pub rdx: u64,
+    pub rbx: u64,
+    pub rsp: u64,
+    pub rbp: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    pub r8: u64,
+    pub r9: u64,
+    pub r10: u64,
+    pub r11: u64,
+    pub r12: u64,
+    pub r13: u64,
+    pub r14: u64,
+    pub r15: u64,
+    /// Exit qualification. For exceptions, the error code and the fault address.
+    pub qual: [u64; 2],
+    pub ctrl: [u32; 2],
+    reserved: u64,
+    pub cr0: u64,
+    pub cr2: u64,
+    pub cr3: u64,
+    pub cr4: u64,
+}
+
+impl ExceptionState {
+    /// Returns the [`Mtd`].
+    pub fn mtd(&self) -> Mtd {
+        Mtd::from_bits_truncate(self.mtd)
+    }
+
+    /// Sets the [`Mtd`] that selects which parts are written back during the reply.
+    pub fn set_mtd(&mut self, mtd: Mtd) {
+        self.mtd = mtd.bits();
+    }
+}
+
+/// The User Thread Control Block (UTCB) of an EC.
+#[repr(C, align(4096))]
+pub struct Utcb {
+    head: UtcbHead,
+    data: [u64; UTCB_DATA_WORDS],
+}
+
+// The kernel expects exactly one page.
+const _: () = assert!(size_of::<Utcb>() == UTCB_SIZE);
+const _: () = assert!(size_of::<ExceptionState>() <= UTCB_DATA_WORDS * size_of::<u64>());
+
+impl Utcb {
+    const UNTYPED_BITMASK: u64 = 0xffff;
+    const TYPED_BITMASK: u64 = 0xffff_0000;
+    const TYPED_BITSHIFT: u64 = 16;
+    /// Number of words of one [`TypedItem`].
+    const TYPED_ITEM_WORDS: usize = size_of::<TypedItem>() / size_of::<u64>();
+
+    /// Returns a reference to the UTCB behind the pointer.
+    ///
+    /// # Safety
+    /// The pointer must point to the mapped, page-aligned UTCB of an EC, for example the one
+    /// that Hedron passes to the roottask. There must be no other reference to the UTCB.
+    pub unsafe fn from_ptr<'a>(ptr: *mut u8) -> &'a mut Self {
+        assert_eq!(ptr as usize % UTCB_SIZE, 0, "UTCB must be page-aligned");
+        &mut *(ptr as *mut Self)
+    }
+
+    /// Returns the number of untyped items of the current message.
+    pub fn untyped_count(&self) -> usize {
+        (self.head.items & Self::UNTYPED_BITMASK) as usize
+    }
+
+    /// Returns the number of typed items of the current message.
+    pub fn typed_count(&self) -> usize {
+        ((self.head.items & Self::TYPED_BITMASK) >> Self::TYPED_BITSHIFT) as usize
+    }
+
+    /// Sets the number of untyped and typed items. Checks that both fit into the UTCB.
+    fn set_counts(&mut self, untyped: usize, typed: usize) -> Result<(), UtcbError> {
+        if untyped + typed * Self::TYPED_ITEM_WORDS > UTCB_DATA_WORDS {
+            return Err(UtcbError::MessageTooLarge);
+        }
+        self.head.items = (untyped as u64 & Self::UNTYPED_BITMASK)
+            | (((typed as u64) << Self::TYPED_BITSHIFT) & Self::TYPED_BITMASK);
+        Ok(())
+    }
+
+    /// Removes all untyped and typed items. The receive windows stay untouched.
+    pub fn clear(&mut self) {
+        self.head.items = 0;
+    }
+
+    /// Returns the untyped items of the current message.
+    pub fn untyped(&self) -> &[u64] {
+        // the kernel never reports more items than fit into the UTCB
+        let count = self.untyped_count().min(UTCB_DATA_WORDS);
+        &self.data[..count]
+    }
+
+    /// Returns the untyped items of the current message as mutable slice.
+    pub fn untyped_mut(&mut self) -> &mut [u64] {
+        let count = self.untyped_count().min(UTCB_DATA_WORDS);
+        &mut self.data[..count]
+    }
+
+    /// Replaces the untyped items of the current message. Typed items are kept.
+    pub fn set_untyped(&mut self, words: &[u64]) -> Result<(), UtcbError> {
+        self.set_counts(words.len(), self.typed_count())?;
+        self.data[..words.len()].copy_from_slice(words);
+        Ok(())
+    }
+
+    /// Appends one untyped item to the current message.
+    pub fn push_untyped(&mut self, word: u64) -> Result<(), UtcbError> {
+        let index = self.untyped_count();
+        self.set_counts(index + 1, self.typed_count())?;
+        self.data[index] = word;
+        Ok(())
+    }
+
+    /// Returns the index of the first word of the typed item with the given index.
+    /// Typed items grow downwards from the end of the UTCB.
+    fn typed_item_index(index: usize) -> usize {
+        UTCB_DATA_WORDS - (index + 1) * Self::TYPED_ITEM_WORDS
+    }
+
+    /// Writes the typed item at the given index. The caller must ensure that the index
+    /// fits into the UTCB.
+    fn write_typed_item(&mut self, index: usize, item: TypedItem) {
+        let i = Self::typed_item_index(index);
+        self.data[i] = item.crd;
+        self.data[i + 1] = item.flags;
+    }
+
+    /// Replaces the current message with the given untyped and typed items. This is the
+    /// typical way to prepare the UTCB before an IPC call or reply.
+    pub fn set_message(&mut self, untyped: &[u64], typed: &[TypedItem]) -> Result<(), UtcbError> {
+        self.set_counts(untyped.len(), typed.len())?;
+        self.data[..untyped.len()].copy_from_slice(untyped);
+        for (index, item) in typed.iter().enumerate() {
+            self.write_typed_item(index, *item);
+        }
+        Ok(())
+    }
+
+    /// Returns the typed item with the given index, if it exists.
+    pub fn typed_item(&self, index: usize) -> Option<TypedItem> {
+        if index >= self.typed_count() || index >= UTCB_DATA_WORDS / Self::TYPED_ITEM_WORDS {
+            return None;
+        }
+        let i = Self::typed_item_index(index);
+        Some(TypedItem {
+            crd: self.data[i],
+            flags: self.data[i + 1],
+        })
+    }
+
+    /// Returns an iterator over all typed items of the current message. For a received
+    /// message, these describe the capabilities that were delegated to the receiver.
+    pub fn typed_items(&self) -> impl Iterator<Item = TypedItem> + '_ {
+        (0..self.typed_count()).map_while(|i| self.typed_item(i))
+    }
+
+    /// Appends a typed item to the current message.
+    pub fn push_typed_item(&mut self, item: TypedItem) -> Result<(), UtcbError> {
+        let index = self.typed_count();
+        self.set_counts(self.untyped_count(), index + 1)?;
+        self.write_typed_item(index, item);
+        Ok(())
+    }
+
+    /// Sets the delegation receive window. It describes where capabilities that are
+    /// delegated to this EC via IPC are placed in the own capability space.
+    pub fn set_receive_window<Perm, Spec, ObjSpec>(&mut self, crd: Crd<Perm, Spec, ObjSpec>) {
+        self.head.del = crd.val();
+    }
+
+    /// Returns the raw value of the delegation receive window.
+    pub fn receive_window_val(&self) -> u64 {
+        self.head.del
+    }
+
+    /// Sets the translation receive window.
+    pub fn set_translate_window<Perm, Spec, ObjSpec>(&mut self, crd: Crd<Perm, Spec, ObjSpec>) {
+        self.head.xlt = crd.val();
+    }
+
+    /// Returns the raw value of the translation receive window.
+    pub fn translate_window_val(&self) -> u64 {
+        self.head.xlt
+    }
+
+    /// Returns the architectural state of the EC that caused an exception. Only valid
+    /// inside the handler of an exception or startup portal.
+    pub fn exception_state(&self) -> &ExceptionState {
+        unsafe { &*(self.data.as_ptr() as *const ExceptionState) }
+    }
+
+    /// Returns the architectural state of the EC that caused an exception. Modifications
+    /// are written back during the reply, if they are selected by [`ExceptionState::mtd`].
+    pub fn exception_state_mut(&mut self) -> &mut ExceptionState {
+        unsafe { &mut *(self.data.as_mut_ptr() as *mut ExceptionState) }
+    }
+
+    /// Returns the thread-local storage word.
+    pub fn tls(&self) -> u64 {
+        self.head.tls
+    }
+
+    /// Sets the thread-local storage word.
+    pub fn set_tls(&mut self, tls: u64) {
+        self.head.tls = tls;
+    }
+}
+
+impl Debug for Utcb {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Utcb")
+            .field("untyped_count", &self.untyped_count())
+            .field("typed_count", &self.typed_count())
+            .field("xlt", &(self.head.xlt as *const u8))
+            .field("del", &(self.head.del as *const u8))
+            .field("tls", &(self.head.tls as *const u8))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem::MaybeUninit;
+    use core::ptr::addr_of;
+
+    #[test]
+    fn test_exception_state_layout() {
+        let state = MaybeUninit::<ExceptionState>::uninit();
+        let base = state.as_ptr() as usize;
+        let offset = |field: *const u64| field as usize - base;
+        unsafe {
+            let state = state.as_ptr();
+            assert_eq!(offset(addr_of!((*state).rip)), 0x10);
+            assert_eq!(offset(addr_of!((*state).rax)), 0x30);
+            assert_eq!(offset(addr_of!((*state).rsp)), 0x50);
+            assert_eq!(offset(addr_of!((*state).r15)), 0xa8);
+            assert_eq!(offset(addr_of!((*state).qual[0])), 0xb0);
+            assert_eq!(offset(addr_of!((*state).cr0)), 0xd0);
+        }
+    }
+}
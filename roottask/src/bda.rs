@@ -26,54 +26,34 @@ SOFTWARE.
 //! This is required to find the serial port on real hardware where it might not be the default
 //! I/O port at 0x3f8.
 
-use crate::hedron::capability::{CrdMem, MemCapPermissions};
-use crate::{pd_ctrl_delegate, DelegateFlags, ROOTTASK_CAPSEL};
-use core::sync::atomic::{AtomicBool, Ordering};
+use crate::vspace::{self, MapError};
+use libhedron::capability::MemCapPermissions;
+use libhedron::pd_ctrl::DelegateFlags;
 
 /// Physical address of the BDA.
 const BIOS_DATA_AREA_ADDRESS: u64 = 0x0400;
-/// Page number of [BIOS_DATA_AREA_ADDRESS]. Here, this is physical frame 0.
-const BIOS_DATA_AREA_ADDRESS_PAGE_NUM: u64 = BIOS_DATA_AREA_ADDRESS / 4096;
 
-/// A virtual address that I use to map the BIOS Data Area to.
-const DEST_ADDR: u64 = 0x1000_0000;
-/// Page number of [DEST_ADDR].
-const DEST_ADDR_PAGE_NUM: u64 = DEST_ADDR / 4096;
-
-/// Stores if the mapping of the boot data area already happened.
-static BDA_MAPPING_DONE: AtomicBool = AtomicBool::new(false);
-
-/// Finds the serial port from the BIOS data area. Uses the same mechanism as Hedron does
-/// internally. This doesn't work on modern UEFI boot flows by default.
-pub fn get_bda<'a>() -> &'a BiosDataArea {
-    if !BDA_MAPPING_DONE.load(Ordering::SeqCst) {
-        map_boot_data_area();
-        BDA_MAPPING_DONE.store(true, Ordering::SeqCst);
-    }
-
-    // page offset
-    let page_offset = BIOS_DATA_AREA_ADDRESS & 0xfff;
-    let bios_data_area = DEST_ADDR | page_offset;
-    let bios_data_area = bios_data_area as *const BiosDataArea;
-    let bios_data_area = unsafe { bios_data_area.as_ref() }.unwrap();
-
-    bios_data_area
-}
-
-/// Performs a PD_CTRL_DELEGATE system call to map the memory of the BDA to [DEST_ADDR].
-fn map_boot_data_area() {
-    // ignore errors because it is too early to print errors.. serial device not ready
-    let _ = pd_ctrl_delegate(
-        ROOTTASK_CAPSEL,
-        ROOTTASK_CAPSEL,
-        CrdMem::new(BIOS_DATA_AREA_ADDRESS_PAGE_NUM, 0, MemCapPermissions::READ),
-        CrdMem::new(DEST_ADDR_PAGE_NUM, 0, MemCapPermissions::READ),
-        DelegateFlags::new(true, false, false, true, 0),
-    );
+/// Maps the BDA into the address space of the roottask, copies it, and removes the mapping
+/// again. Fails if the BDA can't be mapped. This may happen before the logger is
+/// initialized, hence, the caller is responsible for reporting the error.
+pub fn get_bda() -> Result<BiosDataArea, MapError> {
+    let addr = vspace::map(
+        BIOS_DATA_AREA_ADDRESS,
+        core::mem::size_of::<BiosDataArea>() as u64,
+        MemCapPermissions::READ,
+        DelegateFlags::builder().hypervisor(true).build(),
+    )?;
+
+    let bios_data_area = unsafe { core::ptr::read_volatile(addr as *const BiosDataArea) };
+
+    // the BDA is not needed anymore
+    vspace::unmap(addr);
+    Ok(bios_data_area)
 }
 
 /// Bios Data Area.
 /// More Info: <https://www.lowlevel.eu/wiki/BIOS_Data_Area>
+#[derive(Debug, Copy, Clone)]
 #[repr(C)]
 pub struct BiosDataArea {
     pub(crate) com_1_port: u16,
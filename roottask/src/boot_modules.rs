@@ -0,0 +1,167 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Module for the boot modules that the bootloader passed to Hedron, for example with
+//! `module2` in GRUB or with `-initrd` in QEMU. The first boot module is the roottask itself.
+//! Further modules are configurations or binaries of child PDs.
+
+use crate::vspace::{self, MapError};
+use alloc::string::String;
+use core::ops::Range;
+use libhedron::capability::MemCapPermissions;
+use libhedron::hip::{Hip, HipMem};
+use libhedron::pd_ctrl::DelegateFlags;
+
+/// Command lines are cut off after this many bytes. The frame allocator keeps this many bytes
+/// after the start of each command line reserved.
+pub const CMDLINE_MAX_LEN: u64 = 0x1000;
+
+/// A boot module with its command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootModule {
+    /// Physical memory of the module.
+    pub phys: Range<u64>,
+    /// Command line of the module. The first word is usually the path of the file.
+    pub cmdline: String,
+}
+
+impl BootModule {
+    /// Returns the file name of the module, i.e., the last path component of the first
+    /// word of the command line.
+    pub fn name(&self) -> &str {
+        let path = self.cmdline.split_whitespace().next().unwrap_or("");
+        path.rsplit('/').next().unwrap_or(path)
+    }
+
+    /// Returns the size of the module in bytes.
+    pub fn size(&self) -> u64 {
+        self.phys.end - self.phys.start
+    }
+
+    /// Maps the module read-only into the address space of the roottask.
+    pub fn map(&self) -> Result<MappedModule, MapError> {
+        let addr = vspace::map(
+            self.phys.start,
+            self.size(),
+            MemCapPermissions::READ,
+            DelegateFlags::builder().hypervisor(true).build(),
+        )?;
+        Ok(MappedModule {
+            addr,
+            len: self.size() as usize,
+        })
+    }
+}
+
+/// A boot module that is mapped into the address space of the roottask. Unmapped on drop.
+#[derive(Debug)]
+pub struct MappedModule {
+    addr: u64,
+    len: usize,
+}
+
+impl MappedModule {
+    /// Returns the content of the module.
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.addr as *const u8, self.len) }
+    }
+}
+
+impl Drop for MappedModule {
+    fn drop(&mut self) {
+        vspace::unmap(self.addr);
+    }
+}
+
+/// Returns an iterator over all boot modules, including the roottask. The command lines
+/// are mapped temporarily to copy them.
+pub fn boot_modules(hip: &Hip) -> impl Iterator<Item = BootModule> + '_ {
+    hip.boot_modules().map(|mem| BootModule {
+        phys: mem.addr()..mem.end(),
+        cmdline: read_cmdline(mem),
+    })
+}
+
+/// Returns the first boot module with the given [`BootModule::name`].
+#[allow(dead_code)]
+pub fn find(hip: &Hip, name: &str) -> Option<BootModule> {
+    boot_modules(hip).find(|module| module.name() == name)
+}
+
+/// Copies the command line of the boot module. Returns an empty string, if there is no
+/// command line or if it can't be mapped.
+fn read_cmdline(mem: &HipMem) -> String {
+    if mem.aux() == 0 {
+        return String::new();
+    }
+    let addr = match vspace::map(
+        mem.aux() as u64,
+        CMDLINE_MAX_LEN,
+        MemCapPermissions::READ,
+        DelegateFlags::builder().hypervisor(true).build(),
+    ) {
+        Ok(addr) => addr,
+        Err(e) => {
+            log::warn!("can't map the command line of {mem:?}: {e:?}");
+            return String::new();
+        }
+    };
+    let bytes = unsafe { core::slice::from_raw_parts(addr as *const u8, CMDLINE_MAX_LEN as usize) };
+    let cmdline = cmdline_from_bytes(bytes);
+    vspace::unmap(addr);
+    cmdline
+}
+
+/// Interprets the bytes up to the first NUL byte as command line.
+fn cmdline_from_bytes(bytes: &[u8]) -> String {
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..len]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(cmdline: &str) -> BootModule {
+        BootModule {
+            phys: 0x200_0000..0x200_1000,
+            cmdline: cmdline.into(),
+        }
+    }
+
+    #[test]
+    fn test_name() {
+        assert_eq!(module("/roottask").name(), "roottask");
+        assert_eq!(module("/boot/child.elf --verbose").name(), "child.elf");
+        assert_eq!(module("build/hmr roottask").name(), "hmr");
+        assert_eq!(module("config").name(), "config");
+        assert_eq!(module("").name(), "");
+    }
+
+    #[test]
+    fn test_cmdline_from_bytes() {
+        assert_eq!(cmdline_from_bytes(b"/child arg\0garbage"), "/child arg");
+        assert_eq!(cmdline_from_bytes(b"no terminator"), "no terminator");
+        assert_eq!(cmdline_from_bytes(b"\0"), "");
+    }
+}
@@ -0,0 +1,244 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Module for the allocation of capability selectors in the capability space of the
+//! roottask. See [`CapSelAllocator`].
+
+use crate::sync::SpinLock;
+use libhedron::capability::{CapSel, MAX_CRD_ORDER};
+use libhedron::hip::Hip;
+use libhedron::ROOTTASK_CAPSEL;
+
+/// Number of capability selectors that are managed by the allocator. The capability space
+/// is much bigger, but a bitmap for all of them would cost 8 MiB. The first 2^16 selectors
+/// are more than enough for the roottask.
+const MANAGED_CAP_SELS: usize = 1 << 16;
+
+/// Global allocator for the capability space of the roottask. Initialized by [`init`].
+static CAP_SEL_ALLOCATOR: SpinLock<CapSelAllocator> = SpinLock::new(CapSelAllocator::new());
+
+/// Initializes the global allocator with the layout of the capability space from the HIP.
+pub fn init(hip: &Hip) {
+    CAP_SEL_ALLOCATOR.lock().init(hip);
+}
+
+/// Allocates a single free capability selector. Returns `None` if the managed part of the
+/// capability space is exhausted.
+pub fn alloc() -> Option<CapSel> {
+    CAP_SEL_ALLOCATOR.lock().alloc()
+}
+
+/// Allocates `2^order` consecutive free capability selectors, whose base is aligned to `2^order`.
+/// Such ranges can be described by a single [`libhedron::capability::Crd`].
+#[allow(dead_code)]
+pub fn alloc_order(order: u8) -> Option<CapSel> {
+    CAP_SEL_ALLOCATOR.lock().alloc_order(order)
+}
+
+/// Frees a capability selector allocated by [`alloc`].
+pub fn free(sel: CapSel) {
+    CAP_SEL_ALLOCATOR.lock().free(sel)
+}
+
+/// Frees a range allocated by [`alloc_order`].
+#[allow(dead_code)]
+pub fn free_order(base: CapSel, order: u8) {
+    CAP_SEL_ALLOCATOR.lock().free_order(base, order)
+}
+
+/// Bitmap-based allocator for capability selectors. A set bit means the selector is in use.
+/// The selectors that Hedron pre-populates for the roottask are reserved during [`Self::init`].
+pub struct CapSelAllocator {
+    bitmap: [u64; MANAGED_CAP_SELS / 64],
+    /// Exclusive upper bound of the selectors that are handed out.
+    limit: CapSel,
+}
+
+impl CapSelAllocator {
+    /// Creates a new allocator where all selectors are free. Call [`Self::init`] before use.
+    pub const fn new() -> Self {
+        Self {
+            bitmap: [0; MANAGED_CAP_SELS / 64],
+            limit: MANAGED_CAP_SELS as CapSel,
+        }
+    }
+
+    /// Reserves the selectors that Hedron uses by convention: the exception portals of the
+    /// initial EC (`0..num_exc_sel`) and the PD, EC, and SC of the roottask right after them.
+    pub fn init(&mut self, hip: &Hip) {
+        assert_eq!(
+            hip.num_exc_sel(),
+            ROOTTASK_CAPSEL,
+            "the roottask PD must follow the exception portals"
+        );
+        self.limit = hip.num_cap_sel().min(MANAGED_CAP_SELS as CapSel);
+        // exception portals + PD + EC + SC
+        self.reserve(0, hip.num_exc_sel() + 3);
+    }
+
+    /// Marks the given range as used.
+    pub fn reserve(&mut self, base: CapSel, count: u64) {
+        let end = (base + count).min(MANAGED_CAP_SELS as CapSel);
+        for sel in base..end {
+            self.set(sel, true);
+        }
+    }
+
+    /// Allocates a single free capability selector.
+    pub fn alloc(&mut self) -> Option<CapSel> {
+        let (index, word) = self
+            .bitmap
+            .iter()
+            .enumerate()
+            .find(|(_, word)| **word != u64::MAX)?;
+        let sel = (index * 64) as CapSel + word.trailing_ones() as CapSel;
+        if sel >= self.limit {
+            return None;
+        }
+        self.set(sel, true);
+        Some(sel)
+    }
+
+    /// Allocates `2^order` consecutive free capability selectors, whose base is aligned
+    /// to `2^order`.
+    pub fn alloc_order(&mut self, order: u8) -> Option<CapSel> {
+        assert!(order <= MAX_CRD_ORDER, "order too big!");
+        let count = 1_u64 << order;
+        let base = (0..self.limit)
+            .step_by(count as usize)
+            .take_while(|base| base + count <= self.limit)
+            .find(|base| self.is_free(*base, count))?;
+        for sel in base..base + count {
+            self.set(sel, true);
+        }
+        Some(base)
+    }
+
+    /// Frees a single capability selector.
+    pub fn free(&mut self, sel: CapSel) {
+        self.free_order(sel, 0)
+    }
+
+    /// Frees `2^order` capability selectors starting at `base`.
+    pub fn free_order(&mut self, base: CapSel, order: u8) {
+        let count = 1_u64 << order;
+        for sel in base..base + count {
+            assert!(self.get(sel), "double free of capability selector {sel}");
+            self.set(sel, false);
+        }
+    }
+
+    /// Checks if all selectors in the range are free.
+    fn is_free(&self, base: CapSel, count: u64) -> bool {
+        // fast path for ranges of whole words
+        if base % 64 == 0 && count % 64 == 0 {
+            let first = base as usize / 64;
+            let words = count as usize / 64;
+            return self.bitmap[first..first + words].iter().all(|w| *w == 0);
+        }
+        (base..base + count).all(|sel| !self.get(sel))
+    }
+
+    fn get(&self, sel: CapSel) -> bool {
+        let sel = sel as usize;
+        self.bitmap[sel / 64] & (1 << (sel % 64)) != 0
+    }
+
+    fn set(&mut self, sel: CapSel, used: bool) {
+        let sel = sel as usize;
+        if used {
+            self.bitmap[sel / 64] |= 1 << (sel % 64);
+        } else {
+            self.bitmap[sel / 64] &= !(1 << (sel % 64));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::simulator::{self, SimulatorConfig};
+
+    fn allocator() -> CapSelAllocator {
+        simulator::reset(SimulatorConfig::default());
+        let mut allocator = CapSelAllocator::new();
+        allocator.init(simulator::with(|sim| sim.hip()));
+        allocator
+    }
+
+    #[test]
+    fn test_init_reserves_roottask_selectors() {
+        let mut allocator = allocator();
+        // exception portals + PD + EC + SC
+        let reserved = ROOTTASK_CAPSEL + 3;
+        assert!((0..reserved).all(|sel| allocator.get(sel)));
+        assert_eq!(allocator.alloc(), Some(reserved));
+    }
+
+    #[test]
+    fn test_alloc_and_free_reuse() {
+        let mut allocator = allocator();
+        let a = allocator.alloc().unwrap();
+        let b = allocator.alloc().unwrap();
+        assert_eq!(b, a + 1);
+        allocator.free(a);
+        assert_eq!(allocator.alloc(), Some(a));
+        assert_eq!(allocator.alloc(), Some(b + 1));
+    }
+
+    #[test]
+    fn test_alloc_order_is_naturally_aligned() {
+        let mut allocator = allocator();
+        let single = allocator.alloc().unwrap();
+        let base = allocator.alloc_order(4).unwrap();
+        assert_eq!(base % 16, 0);
+        assert!(base > single);
+        assert!((base..base + 16).all(|sel| allocator.get(sel)));
+
+        allocator.free_order(base, 4);
+        assert!(allocator.is_free(base, 16));
+        assert_eq!(allocator.alloc_order(4), Some(base));
+    }
+
+    #[test]
+    fn test_exhausted() {
+        let mut allocator = CapSelAllocator::new();
+        allocator.limit = 70;
+        allocator.reserve(0, 64);
+        assert!(allocator.alloc_order(3).is_none());
+        assert_eq!(allocator.alloc_order(2), Some(64));
+        assert_eq!(allocator.alloc(), Some(68));
+        assert_eq!(allocator.alloc(), Some(69));
+        assert_eq!(allocator.alloc(), None);
+        assert_eq!(allocator.alloc_order(0), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "double free")]
+    fn test_double_free_panics() {
+        let mut allocator = allocator();
+        let sel = allocator.alloc().unwrap();
+        allocator.free(sel);
+        allocator.free(sel);
+    }
+}
@@ -23,9 +23,11 @@ SOFTWARE.
 */
 //! Module that enables QEMUs debugcon port. See [DebugconPort].
 
-use crate::{pd_ctrl_delegate, CrdPortIO, DelegateFlags, ROOTTASK_CAPSEL};
 use core::fmt::Write;
 use core::sync::atomic::{AtomicBool, Ordering};
+use libhedron::delegate::delegate_ports;
+use libhedron::pd_ctrl::DelegateFlags;
+use libhedron::ROOTTASK_CAPSEL;
 
 const QEMU_DEBUGCON_PORT: u16 = 0xe9;
 
@@ -67,14 +69,27 @@ pub fn get_debugcon_port() -> DebugconPort {
 /// Uses a PD_CTRL_DELEGATE syscall to delegate the rights for the corresponding I/O ports into
 /// the I/O map of the roottask.
 fn delegate_port_rights(port: u16) {
-    let _ = pd_ctrl_delegate(
+    let _ = delegate_ports(
+        port..=port,
         ROOTTASK_CAPSEL,
-        ROOTTASK_CAPSEL,
-        // order 3: means 2^3 == 8 => map 8 ports at once => optimization of NOVA/Hedron syscall interface
-        // Background: Serial devices us behind 8 ports because it has 8 hardware registers
-        CrdPortIO::new(port, 3),
-        CrdPortIO::new(port, 3),
         // most important boolean flag: "use hypervisor as src"
-        DelegateFlags::new(true, false, false, true, 0),
+        DelegateFlags::builder().hypervisor(true).build(),
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::simulator::{self, SimulatorConfig};
+
+    #[test]
+    fn test_get_debugcon_port_delegates_port() {
+        simulator::reset(SimulatorConfig::default());
+        let _ = get_debugcon_port();
+        simulator::with(|sim| {
+            assert!(sim.has_port(ROOTTASK_CAPSEL, QEMU_DEBUGCON_PORT));
+            // only the single port of debugcon
+            assert!(!sim.has_port(ROOTTASK_CAPSEL, QEMU_DEBUGCON_PORT + 1));
+        });
+    }
+}
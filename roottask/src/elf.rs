@@ -0,0 +1,261 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Minimal parser for statically linked ELF64 executables for x86_64. See [`ElfFile`].
+//!
+//! Only what the loader needs is parsed: the entry point and the `PT_LOAD` segments. All
+//! offsets and sizes are validated once in [`ElfFile::parse`], so that the accessors don't
+//! need to fail.
+
+use libhedron::capability::MemCapPermissions;
+
+/// Size of the ELF64 file header.
+const EHDR_SIZE: usize = 64;
+/// Size of an ELF64 program header.
+const PHDR_SIZE: usize = 56;
+/// Type of a loadable segment.
+const PT_LOAD: u32 = 1;
+/// `e_type` of an executable file.
+const ET_EXEC: u16 = 2;
+/// `e_machine` of x86_64.
+const EM_X86_64: u16 = 62;
+
+/// Errors of [`ElfFile::parse`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ElfError {
+    /// The file is too small for the headers it describes.
+    TooShort,
+    /// The magic bytes are missing.
+    NoElf,
+    /// The file is not a little-endian ELF64 file for x86_64.
+    UnsupportedArchitecture,
+    /// The file is not an executable. Relocatable and position-independent files
+    /// are not supported.
+    NotExecutable,
+    /// The program headers have an unexpected size.
+    InvalidProgramHeaderSize(u16),
+    /// The file content of the segment is outside of the file or bigger than the segment.
+    InvalidSegment(LoadSegment),
+}
+
+/// A `PT_LOAD` segment.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LoadSegment {
+    /// Virtual start address.
+    pub vaddr: u64,
+    /// Size in memory. Everything after [`Self::file_size`] is zeroed.
+    pub mem_size: u64,
+    /// Offset of the content in the file.
+    pub file_offset: u64,
+    /// Size of the content in the file.
+    pub file_size: u64,
+    /// Raw segment flags (`p_flags`).
+    pub flags: u32,
+}
+
+impl LoadSegment {
+    /// Returns the memory permissions from the segment flags.
+    pub fn permissions(&self) -> MemCapPermissions {
+        MemCapPermissions::from_elf_segment_permissions(self.flags as u8)
+    }
+}
+
+/// A validated ELF64 executable.
+#[derive(Debug, Copy, Clone)]
+pub struct ElfFile<'a> {
+    bytes: &'a [u8],
+    entry: u64,
+    phoff: usize,
+    phnum: usize,
+}
+
+impl<'a> ElfFile<'a> {
+    /// Parses and validates the file header and all program headers.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, ElfError> {
+        if bytes.len() < EHDR_SIZE {
+            return Err(ElfError::TooShort);
+        }
+        if bytes[0..4] != *b"\x7fELF" {
+            return Err(ElfError::NoElf);
+        }
+        // ELFCLASS64, ELFDATA2LSB, x86_64
+        if bytes[4] != 2 || bytes[5] != 1 || read_u16(bytes, 18) != EM_X86_64 {
+            return Err(ElfError::UnsupportedArchitecture);
+        }
+        if read_u16(bytes, 16) != ET_EXEC {
+            return Err(ElfError::NotExecutable);
+        }
+        let phentsize = read_u16(bytes, 54);
+        if phentsize as usize != PHDR_SIZE {
+            return Err(ElfError::InvalidProgramHeaderSize(phentsize));
+        }
+        let elf = Self {
+            bytes,
+            entry: read_u64(bytes, 24),
+            phoff: read_u64(bytes, 32) as usize,
+            phnum: read_u16(bytes, 56) as usize,
+        };
+        let phdrs_end = elf
+            .phnum
+            .checked_mul(PHDR_SIZE)
+            .and_then(|size| size.checked_add(elf.phoff));
+        if phdrs_end.map_or(true, |end| end > bytes.len()) {
+            return Err(ElfError::TooShort);
+        }
+        for segment in elf.load_segments() {
+            let file_end = segment.file_offset.checked_add(segment.file_size);
+            if file_end.map_or(true, |end| end > bytes.len() as u64)
+                || segment.file_size > segment.mem_size
+                || segment.vaddr.checked_add(segment.mem_size).is_none()
+            {
+                return Err(ElfError::InvalidSegment(segment));
+            }
+        }
+        Ok(elf)
+    }
+
+    /// Returns the virtual address of the entry point.
+    pub fn entry(&self) -> u64 {
+        self.entry
+    }
+
+    /// Returns an iterator over all `PT_LOAD` segments.
+    pub fn load_segments(&self) -> impl Iterator<Item = LoadSegment> + 'a {
+        let bytes = self.bytes;
+        let phoff = self.phoff;
+        (0..self.phnum)
+            .map(move |i| phoff + i * PHDR_SIZE)
+            .filter(move |&phdr| read_u32(bytes, phdr) == PT_LOAD)
+            .map(move |phdr| LoadSegment {
+                flags: read_u32(bytes, phdr + 4),
+                file_offset: read_u64(bytes, phdr + 8),
+                vaddr: read_u64(bytes, phdr + 16),
+                file_size: read_u64(bytes, phdr + 32),
+                mem_size: read_u64(bytes, phdr + 40),
+            })
+    }
+
+    /// Returns the file content of the segment.
+    pub fn segment_data(&self, segment: &LoadSegment) -> &'a [u8] {
+        let start = segment.file_offset as usize;
+        &self.bytes[start..start + segment.file_size as usize]
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds an executable with a text segment and a data segment with `.bss`.
+    fn test_elf() -> Vec<u8> {
+        let mut elf = vec![0; 0x200];
+        elf[0..4].copy_from_slice(b"\x7fELF");
+        elf[4] = 2;
+        elf[5] = 1;
+        elf[16..18].copy_from_slice(&ET_EXEC.to_le_bytes());
+        elf[18..20].copy_from_slice(&EM_X86_64.to_le_bytes());
+        elf[24..32].copy_from_slice(&0x40_1000_u64.to_le_bytes());
+        elf[32..40].copy_from_slice(&(EHDR_SIZE as u64).to_le_bytes());
+        elf[54..56].copy_from_slice(&(PHDR_SIZE as u16).to_le_bytes());
+        elf[56..58].copy_from_slice(&3_u16.to_le_bytes());
+
+        // (type, flags, offset, vaddr, filesz, memsz)
+        let phdrs = [
+            (PT_LOAD, 0b101, 0x180, 0x40_1000, 0x40, 0x40),
+            // PT_GNU_STACK
+            (0x6474_e551, 0b110, 0, 0, 0, 0),
+            (PT_LOAD, 0b110, 0x1c0, 0x40_2000, 0x40, 0x1000),
+        ];
+        for (i, (typ, flags, offset, vaddr, filesz, memsz)) in phdrs.into_iter().enumerate() {
+            let phdr = &mut elf[EHDR_SIZE + i * PHDR_SIZE..][..PHDR_SIZE];
+            phdr[0..4].copy_from_slice(&(typ as u32).to_le_bytes());
+            phdr[4..8].copy_from_slice(&(flags as u32).to_le_bytes());
+            phdr[8..16].copy_from_slice(&(offset as u64).to_le_bytes());
+            phdr[16..24].copy_from_slice(&(vaddr as u64).to_le_bytes());
+            phdr[32..40].copy_from_slice(&(filesz as u64).to_le_bytes());
+            phdr[40..48].copy_from_slice(&(memsz as u64).to_le_bytes());
+        }
+        elf
+    }
+
+    #[test]
+    fn test_parse_load_segments() {
+        let bytes = test_elf();
+        let elf = ElfFile::parse(&bytes).unwrap();
+        assert_eq!(elf.entry(), 0x40_1000);
+        let segments = elf.load_segments().collect::<Vec<_>>();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(
+            segments[0].permissions(),
+            MemCapPermissions::READ | MemCapPermissions::EXECUTE
+        );
+        assert_eq!(segments[1].vaddr, 0x40_2000);
+        assert_eq!(segments[1].mem_size, 0x1000);
+        assert_eq!(elf.segment_data(&segments[1]).len(), 0x40);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_files() {
+        let bytes = test_elf();
+        assert_eq!(
+            ElfFile::parse(&bytes[..40]).unwrap_err(),
+            ElfError::TooShort
+        );
+
+        let mut no_elf = bytes.clone();
+        no_elf[1] = b'X';
+        assert_eq!(ElfFile::parse(&no_elf).unwrap_err(), ElfError::NoElf);
+
+        let mut elf32 = bytes.clone();
+        elf32[4] = 1;
+        assert_eq!(
+            ElfFile::parse(&elf32).unwrap_err(),
+            ElfError::UnsupportedArchitecture
+        );
+
+        let mut pie = bytes.clone();
+        pie[16] = 3;
+        assert_eq!(ElfFile::parse(&pie).unwrap_err(), ElfError::NotExecutable);
+
+        // the content of the data segment is cut off
+        assert!(matches!(
+            ElfFile::parse(&bytes[..0x1d0]).unwrap_err(),
+            ElfError::InvalidSegment(LoadSegment {
+                vaddr: 0x40_2000,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,302 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Module for the handling of exceptions of other ECs. See [`ExceptionPortals`].
+//!
+//! Hedron delivers the exceptions of an EC, its startup, and its recall as portal calls to
+//! the selectors `evt_base..evt_base + NUM_EVENTS` in the capability space of its PD. For
+//! each such event range, the roottask creates a local EC with one portal per event. The
+//! portal ID is the address of the dispatch data of the range combined with the event, so
+//! that a single entry function dispatches all calls to the Rust handler of the range.
+//!
+//! [`protect_roottask`] uses the same mechanism for the main EC of the roottask. Then a
+//! fault of the roottask, for example a stack overflow, is logged instead of silently
+//! killing the roottask. The path from the portal entry to the handler neither allocates
+//! nor takes a lock, so that it also works, if the roottask faulted inside the heap
+//! allocator or while it held any other lock.
+
+use crate::kobject::{Ec, Pt, Sm};
+use crate::stack;
+use crate::vspace::{self, VaUsage};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use libhedron::capability::{CapSel, PTCapPermissions};
+use libhedron::create_ec::EcKind;
+use libhedron::error::HedronError;
+use libhedron::event::{Event, NUM_EVENTS};
+use libhedron::ipc::{ipc_reply, portal_stack_ptr, PortalEntry};
+use libhedron::mtd::Mtd;
+use libhedron::pt_ctrl::PortalId;
+use libhedron::sm_ctrl::sm_ctrl_down;
+use libhedron::utcb::{ExceptionState, Utcb};
+use libhedron::ROOTTASK_CAPSEL;
+
+/// Parts of the architectural state that are transferred for every event. Enough for the
+/// startup, for fixing faults, and for a register dump.
+const EXCEPTION_MTD: Mtd = Mtd::from_bits_truncate(
+    Mtd::GPR_ACDB.bits()
+        | Mtd::GPR_BSD.bits()
+        | Mtd::GPR_R8_R15.bits()
+        | Mtd::RSP.bits()
+        | Mtd::RIP_LEN.bits()
+        | Mtd::RFLAGS.bits()
+        | Mtd::QUAL.bits(),
+);
+/// Event base of the main EC of the roottask. Hedron sets it when it creates the roottask.
+const ROOTTASK_EVT_BASE: CapSel = 0;
+/// Size of the stack of each local EC that handles events.
+const HANDLER_STACK_SIZE: usize = 0x4000;
+
+/// Bits of the portal ID that hold the event. The other bits hold the address of the
+/// [`Dispatch`] data.
+const EVENT_MASK: PortalId = core::mem::align_of::<Dispatch>() as PortalId - 1;
+
+// every event must fit into the bits below the address of the dispatch data
+const _: () = assert!(NUM_EVENTS <= EVENT_MASK + 1);
+
+/// What happens with the EC after its event was handled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExceptionAction {
+    /// Resume the EC with the (possibly modified) [`ExceptionState`]. To fix a fault, the
+    /// handler maps the missing memory or changes the registers, for example the
+    /// instruction pointer, before it resumes the EC.
+    Resume,
+    /// Never resume the EC. It stays blocked in the portal call until the
+    /// [`ExceptionPortals`] are dropped, and the range is marked as killed.
+    Kill,
+}
+
+/// A handler for the events of an event range. It gets the event and the state of the EC.
+pub type ExceptionHandler = dyn Fn(Event, &mut ExceptionState) -> ExceptionAction + Send + Sync;
+
+/// Errors of [`ExceptionPortals::create`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExceptionPortalsError {
+    /// There is no free virtual address range for the UTCB of the local EC.
+    OutOfVirtualMemory,
+    /// Creating or delegating a kernel object failed.
+    Hedron(HedronError),
+}
+
+impl From<HedronError> for ExceptionPortalsError {
+    fn from(e: HedronError) -> Self {
+        Self::Hedron(e)
+    }
+}
+
+/// State of an event range that the portal entry needs. The portal entry finds it via the
+/// portal ID. It lives on the heap until the portals and the local EC are revoked.
+#[repr(align(64))]
+struct Dispatch {
+    name: Box<str>,
+    utcb_addr: u64,
+    kill_sm: CapSel,
+    killed: AtomicBool,
+    handler: Arc<ExceptionHandler>,
+}
+
+/// Stack of a local EC that handles events.
+#[repr(C, align(4096))]
+struct HandlerStack([u8; HANDLER_STACK_SIZE]);
+
+/// The portals for the event range of one or more ECs of a PD, together with the local EC
+/// that handles them. Dropping it revokes the portals.
+pub struct ExceptionPortals {
+    // The order of the fields is the order in which they are dropped.
+    pts: Vec<Pt>,
+    ec: Ec,
+    #[allow(dead_code)]
+    kill_sm: Sm,
+    #[allow(dead_code)]
+    utcb: UtcbAddr,
+    #[allow(dead_code)]
+    stack: Box<HandlerStack>,
+    dispatch: Box<Dispatch>,
+}
+
+impl ExceptionPortals {
+    /// Creates a portal for every event and delegates them to the selectors
+    /// `evt_base..evt_base + NUM_EVENTS` of the PD `pd`. `name` identifies the range in log
+    /// messages. The handler runs on a local EC of the roottask on CPU `cpu`.
+    pub fn create(
+        name: &str,
+        pd: CapSel,
+        evt_base: CapSel,
+        cpu: u16,
+        handler: Arc<ExceptionHandler>,
+    ) -> Result<Self, ExceptionPortalsError> {
+        let stack = Box::new(HandlerStack([0; HANDLER_STACK_SIZE]));
+        let stack_top = stack.0.as_ptr() as u64 + HANDLER_STACK_SIZE as u64;
+        let utcb = vspace::alloc(1, 0, VaUsage::Utcb)
+            .map(UtcbAddr)
+            .ok_or(ExceptionPortalsError::OutOfVirtualMemory)?;
+        let kill_sm = Sm::create(0)?;
+        let ec = Ec::create(
+            ROOTTASK_CAPSEL,
+            EcKind::Local,
+            cpu,
+            utcb.0,
+            portal_stack_ptr(stack_top),
+            0,
+        )?;
+
+        let dispatch = Box::new(Dispatch {
+            name: Box::from(name),
+            utcb_addr: utcb.0,
+            kill_sm: kill_sm.sel(),
+            killed: AtomicBool::new(false),
+            handler,
+        });
+        let dispatch_addr = &*dispatch as *const Dispatch as PortalId;
+        let mut portals = Self {
+            pts: Vec::with_capacity(NUM_EVENTS as usize),
+            ec,
+            kill_sm,
+            utcb,
+            stack,
+            dispatch,
+        };
+        for offset in 0..NUM_EVENTS {
+            let pt = Pt::create(
+                ROOTTASK_CAPSEL,
+                &portals.ec,
+                EXCEPTION_MTD,
+                portal_entry as PortalEntry as usize as u64,
+            )?;
+            pt.set_id(dispatch_addr | offset)?;
+            pt.delegate_to(pd, evt_base + offset, PTCapPermissions::CALL)?;
+            portals.pts.push(pt);
+        }
+        Ok(portals)
+    }
+
+    /// Returns true, if a handler returned [`ExceptionAction::Kill`].
+    pub fn killed(&self) -> bool {
+        self.dispatch.killed.load(Ordering::SeqCst)
+    }
+}
+
+impl core::fmt::Debug for ExceptionPortals {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ExceptionPortals")
+            .field("name", &self.dispatch.name)
+            .field("ec", &self.ec)
+            .finish()
+    }
+}
+
+/// Virtual address of the UTCB of a local EC. Given back to [`vspace`] on drop, after the EC
+/// is gone.
+struct UtcbAddr(u64);
+
+impl Drop for UtcbAddr {
+    fn drop(&mut self) {
+        vspace::free(self.0);
+    }
+}
+
+/// Installs portals for the events of the main EC of the roottask. They log the event and
+/// a register dump and stop the main EC. The portals must stay alive as long as the
+/// roottask runs.
+pub fn protect_roottask() -> Result<ExceptionPortals, ExceptionPortalsError> {
+    ExceptionPortals::create(
+        "roottask",
+        ROOTTASK_CAPSEL,
+        ROOTTASK_EVT_BASE,
+        0,
+        Arc::new(|event, state| {
+            log_exception("roottask", event, state);
+            if event == Event::PageFault && stack::is_guard_page(state.qual[1]) {
+                log::error!(
+                    "roottask: stack overflow, max depth {} of {} bytes",
+                    stack::max_depth(),
+                    stack::size()
+                );
+            }
+            ExceptionAction::Kill
+        }),
+    )
+}
+
+/// Logs the event with the state of the EC. Useful for handlers that can't handle an event.
+pub fn log_exception(name: &str, event: Event, state: &ExceptionState) {
+    log::error!(
+        "{name}: {event:?} (vector {}) at rip={:#x}, rsp={:#x}, error_code={:#x}, cr2={:#x}",
+        event.offset(),
+        state.rip,
+        state.rsp,
+        state.qual[0],
+        state.qual[1]
+    );
+    log::error!(
+        "{name}: rax={:#018x} rbx={:#018x} rcx={:#018x} rdx={:#018x}",
+        state.rax,
+        state.rbx,
+        state.rcx,
+        state.rdx
+    );
+    log::error!(
+        "{name}: rsi={:#018x} rdi={:#018x} rbp={:#018x} rflags={:#x}",
+        state.rsi,
+        state.rdi,
+        state.rbp,
+        state.rflags
+    );
+    log::error!(
+        "{name}: r8 ={:#018x} r9 ={:#018x} r10={:#018x} r11={:#018x}",
+        state.r8,
+        state.r9,
+        state.r10,
+        state.r11
+    );
+    log::error!(
+        "{name}: r12={:#018x} r13={:#018x} r14={:#018x} r15={:#018x}",
+        state.r12,
+        state.r13,
+        state.r14,
+        state.r15
+    );
+}
+
+/// Entry of all event portals. Dispatches to the handler of the event range.
+extern "C" fn portal_entry(id: PortalId) -> ! {
+    let event = Event::from_offset(id & EVENT_MASK);
+    // The dispatch data outlives the portals and the local EC that runs this function.
+    let dispatch = unsafe { &*((id & !EVENT_MASK) as *const Dispatch) };
+
+    let utcb = unsafe { Utcb::from_ptr(dispatch.utcb_addr as *mut u8) };
+    match (dispatch.handler)(event, utcb.exception_state_mut()) {
+        ExceptionAction::Resume => ipc_reply(),
+        ExceptionAction::Kill => {
+            log::error!("{}: stopped after {event:?}", dispatch.name);
+            dispatch.killed.store(true, Ordering::SeqCst);
+            // Never reply. This blocks the EC that caused the event until the semaphore,
+            // and with it the local EC, are revoked.
+            loop {
+                let _ = sm_ctrl_down(dispatch.kill_sm, false, None);
+            }
+        }
+    }
+}
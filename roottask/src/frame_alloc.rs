@@ -0,0 +1,398 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Module for the allocation of physical memory frames. See [`FrameAllocator`].
+//!
+//! The free memory is derived from the memory descriptors of the HIP. Only regions of type
+//! [`HipMemType::Available`] are used. All other regions are cut out of them, because they
+//! may overlap. This excludes the hypervisor and the boot modules. The first boot module is
+//! the roottask and Hedron maps its image directly from there. The command lines of the boot
+//! modules and the first MiB (BIOS data, real mode IVT, ...) are excluded as well.
+//!
+//! Every allocation has an owner, i.e., the capability selector of the PD that uses the
+//! frames. When a child PD dies, [`free_owner`] returns all of its frames at once.
+
+use crate::boot_modules::CMDLINE_MAX_LEN;
+use crate::sync::SpinLock;
+use libhedron::capability::CapSel;
+use libhedron::hip::{Hip, HipMemType};
+use libhedron::PAGE_SIZE;
+
+/// Maximum number of disjoint free ranges. The HIP of a typical machine has a few dozen
+/// memory descriptors.
+const MAX_FREE_RANGES: usize = 128;
+/// Maximum number of allocations that are tracked at the same time.
+const MAX_ALLOCATIONS: usize = 512;
+/// Memory below this address is never handed out.
+const LOW_MEMORY_END: u64 = 0x10_0000;
+
+/// Global frame allocator. Initialized by [`init`].
+static FRAME_ALLOCATOR: SpinLock<FrameAllocator> = SpinLock::new(FrameAllocator::new());
+
+/// Initializes the global allocator with the free memory from the HIP.
+pub fn init(hip: &Hip) {
+    FRAME_ALLOCATOR.lock().init(hip);
+}
+
+/// Allocates a single frame for the PD `owner`. Returns the physical address.
+#[allow(dead_code)]
+pub fn alloc(owner: CapSel) -> Option<u64> {
+    FRAME_ALLOCATOR.lock().alloc_order(0, owner)
+}
+
+/// Allocates `2^order` contiguous frames for the PD `owner`. The physical address is aligned
+/// to `2^order` pages, so that the range can be described by a single
+/// [`libhedron::capability::CrdMem`].
+pub fn alloc_order(order: u8, owner: CapSel) -> Option<u64> {
+    FRAME_ALLOCATOR.lock().alloc_order(order, owner)
+}
+
+/// Frees an allocation made by [`alloc`] or [`alloc_order`].
+pub fn free(addr: u64) {
+    FRAME_ALLOCATOR.lock().free(addr)
+}
+
+/// Frees all allocations of the PD `owner`. Returns the number of freed frames.
+pub fn free_owner(owner: CapSel) -> u64 {
+    FRAME_ALLOCATOR.lock().free_owner(owner)
+}
+
+/// Returns the number of free frames.
+pub fn free_frames() -> u64 {
+    FRAME_ALLOCATOR.lock().free_frames()
+}
+
+/// A range of frames, described by page numbers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct FrameRange {
+    /// First page number.
+    base: u64,
+    /// Number of pages.
+    count: u64,
+}
+
+impl FrameRange {
+    const EMPTY: Self = Self { base: 0, count: 0 };
+
+    fn end(self) -> u64 {
+        self.base + self.count
+    }
+}
+
+/// An allocation with its owner.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Allocation {
+    range: FrameRange,
+    owner: CapSel,
+}
+
+/// Allocator for physical memory frames. The free memory is kept as a sorted list of
+/// disjoint ranges, which stays small, as long as memory is freed in the same granularity
+/// as it was allocated.
+pub struct FrameAllocator {
+    free: [FrameRange; MAX_FREE_RANGES],
+    free_len: usize,
+    allocations: [Option<Allocation>; MAX_ALLOCATIONS],
+}
+
+impl FrameAllocator {
+    /// Creates a new allocator without free memory. Call [`Self::init`] before use.
+    pub const fn new() -> Self {
+        Self {
+            free: [FrameRange::EMPTY; MAX_FREE_RANGES],
+            free_len: 0,
+            allocations: [None; MAX_ALLOCATIONS],
+        }
+    }
+
+    /// Adds all available memory from the HIP and removes everything that must not be
+    /// handed out. See the module description.
+    pub fn init(&mut self, hip: &Hip) {
+        for mem in hip.mem_descs() {
+            if mem.typ() == HipMemType::Available {
+                // only whole pages inside the region
+                let base = (mem.addr() + PAGE_SIZE - 1) / PAGE_SIZE;
+                let end = mem.end() / PAGE_SIZE;
+                if end > base {
+                    self.add_free(FrameRange {
+                        base,
+                        count: end - base,
+                    });
+                }
+            }
+        }
+        self.reserve(0, LOW_MEMORY_END);
+        for mem in hip.mem_descs() {
+            match mem.typ() {
+                HipMemType::Available => {}
+                HipMemType::MultibootModule => {
+                    self.reserve(mem.addr(), mem.size());
+                    // the command line of the module, which may cross a page boundary
+                    if mem.aux() != 0 {
+                        self.reserve(mem.aux() as u64, CMDLINE_MAX_LEN);
+                    }
+                }
+                _ => self.reserve(mem.addr(), mem.size()),
+            }
+        }
+    }
+
+    /// Removes the pages that overlap with the physical range from the free memory.
+    pub fn reserve(&mut self, addr: u64, size: u64) {
+        if size == 0 {
+            return;
+        }
+        let base = addr / PAGE_SIZE;
+        let end = (addr + size + PAGE_SIZE - 1) / PAGE_SIZE;
+        self.remove_free(FrameRange {
+            base,
+            count: end - base,
+        });
+    }
+
+    /// Allocates `2^order` contiguous, naturally aligned frames for `owner` and returns the
+    /// physical address. Uses the first range that fits.
+    pub fn alloc_order(&mut self, order: u8, owner: CapSel) -> Option<u64> {
+        assert!(order < 52, "order too big!");
+        let count = 1_u64 << order;
+        let slot = self.allocations.iter().position(Option::is_none)?;
+        let range = self.free[..self.free_len].iter().find_map(|free| {
+            let base = (free.base + count - 1) & !(count - 1);
+            (base + count <= free.end()).then_some(FrameRange { base, count })
+        })?;
+        self.remove_free(range);
+        self.allocations[slot] = Some(Allocation { range, owner });
+        Some(range.base * PAGE_SIZE)
+    }
+
+    /// Frees the allocation that starts at the physical address.
+    pub fn free(&mut self, addr: u64) {
+        let base = addr / PAGE_SIZE;
+        let slot = self
+            .allocations
+            .iter_mut()
+            .find(|a| matches!(a, Some(a) if a.range.base == base))
+            .unwrap_or_else(|| panic!("no allocation at {addr:#x}"));
+        let allocation = slot.take().unwrap();
+        self.add_free(allocation.range);
+    }
+
+    /// Frees all allocations of `owner`. Returns the number of freed frames.
+    pub fn free_owner(&mut self, owner: CapSel) -> u64 {
+        let mut freed = 0;
+        for i in 0..MAX_ALLOCATIONS {
+            if let Some(allocation) = self.allocations[i] {
+                if allocation.owner == owner {
+                    self.allocations[i] = None;
+                    self.add_free(allocation.range);
+                    freed += allocation.range.count;
+                }
+            }
+        }
+        freed
+    }
+
+    /// Returns the number of free frames.
+    pub fn free_frames(&self) -> u64 {
+        self.free[..self.free_len].iter().map(|r| r.count).sum()
+    }
+
+    /// Returns the owner of the frame at the physical address, if it is allocated.
+    #[allow(dead_code)]
+    pub fn owner(&self, addr: u64) -> Option<CapSel> {
+        let page = addr / PAGE_SIZE;
+        self.allocations
+            .iter()
+            .flatten()
+            .find(|a| (a.range.base..a.range.end()).contains(&page))
+            .map(|a| a.owner)
+    }
+
+    /// Inserts the range into the sorted list of free ranges and merges it with its
+    /// neighbours. The range must not overlap with free memory.
+    fn add_free(&mut self, range: FrameRange) {
+        let index = self.free[..self.free_len]
+            .iter()
+            .position(|r| r.base > range.base)
+            .unwrap_or(self.free_len);
+        if index > 0 {
+            assert!(
+                self.free[index - 1].end() <= range.base,
+                "frames are already free"
+            );
+        }
+        if index < self.free_len {
+            assert!(
+                range.end() <= self.free[index].base,
+                "frames are already free"
+            );
+        }
+
+        let merge_prev = index > 0 && self.free[index - 1].end() == range.base;
+        let merge_next = index < self.free_len && range.end() == self.free[index].base;
+        match (merge_prev, merge_next) {
+            (true, true) => {
+                self.free[index - 1].count += range.count + self.free[index].count;
+                self.remove_index(index);
+            }
+            (true, false) => self.free[index - 1].count += range.count,
+            (false, true) => {
+                self.free[index].base = range.base;
+                self.free[index].count += range.count;
+            }
+            (false, false) => self.insert_index(index, range),
+        }
+    }
+
+    /// Removes the range from the free memory. Parts of the range that are not free are
+    /// ignored.
+    fn remove_free(&mut self, range: FrameRange) {
+        let mut i = 0;
+        while i < self.free_len {
+            let free = self.free[i];
+            if free.end() <= range.base || free.base >= range.end() {
+                i += 1;
+                continue;
+            }
+            let before = FrameRange {
+                base: free.base,
+                count: range.base.saturating_sub(free.base),
+            };
+            let after_base = range.end().max(free.base);
+            let after = FrameRange {
+                base: after_base,
+                count: free.end().saturating_sub(after_base),
+            };
+            self.remove_index(i);
+            if after.count > 0 {
+                self.insert_index(i, after);
+            }
+            if before.count > 0 {
+                self.insert_index(i, before);
+                i += 1;
+            }
+            if after.count > 0 {
+                i += 1;
+            }
+        }
+    }
+
+    fn insert_index(&mut self, index: usize, range: FrameRange) {
+        assert!(self.free_len < MAX_FREE_RANGES, "too many free ranges");
+        self.free.copy_within(index..self.free_len, index + 1);
+        self.free[index] = range;
+        self.free_len += 1;
+    }
+
+    fn remove_index(&mut self, index: usize) {
+        self.free.copy_within(index + 1..self.free_len, index);
+        self.free_len -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::simulator::{self, SimulatorConfig, SimulatorMemDesc};
+
+    fn allocator(memory: &'static [SimulatorMemDesc]) -> FrameAllocator {
+        simulator::reset(SimulatorConfig {
+            memory,
+            ..SimulatorConfig::default()
+        });
+        let mut allocator = FrameAllocator::new();
+        allocator.init(simulator::with(|sim| sim.hip()));
+        allocator
+    }
+
+    const fn mem(addr: u64, size: u64, typ: i32) -> SimulatorMemDesc {
+        SimulatorMemDesc {
+            addr,
+            size,
+            typ,
+            aux: 0,
+        }
+    }
+
+    #[test]
+    fn test_init_excludes_reserved_memory() {
+        const MEMORY: &[SimulatorMemDesc] = &[
+            mem(0, 0x8_0000, 1),
+            mem(0x10_0000, 0x100_0000, 1),
+            // hypervisor and roottask inside the available memory
+            mem(0x20_0000, 0x10_0000, -1),
+            mem(0x40_0000, 0x1800, -2),
+        ];
+        let allocator = allocator(MEMORY);
+        // 16 MiB - 1 MiB hypervisor - 2 pages roottask
+        assert_eq!(allocator.free_frames(), 0x1000 - 0x100 - 2);
+        assert_eq!(allocator.free_len, 3);
+    }
+
+    #[test]
+    fn test_init_excludes_whole_cmdline() {
+        const MEMORY: &[SimulatorMemDesc] = &[
+            mem(0x10_0000, 0x10_0000, 1),
+            // the command line starts right before a page boundary
+            SimulatorMemDesc {
+                aux: 0x10_0ff0,
+                ..mem(0x18_0000, 0x1000, -2)
+            },
+        ];
+        let allocator = allocator(MEMORY);
+        // 1 MiB - 1 page module - 2 pages command line
+        assert_eq!(allocator.free_frames(), 0x100 - 1 - 2);
+    }
+
+    #[test]
+    fn test_alloc_aligned_and_free_owner() {
+        const MEMORY: &[SimulatorMemDesc] = &[mem(0x10_1000, 0x10_0000, 1)];
+        let mut allocator = allocator(MEMORY);
+        let total = allocator.free_frames();
+
+        let single = allocator.alloc_order(0, 100).unwrap();
+        assert_eq!(single, 0x10_1000);
+        let range = allocator.alloc_order(4, 100).unwrap();
+        assert_eq!(range % (16 * PAGE_SIZE), 0);
+        let other = allocator.alloc_order(1, 200).unwrap();
+        assert_eq!(allocator.owner(range + PAGE_SIZE), Some(100));
+        assert_eq!(allocator.owner(other), Some(200));
+        assert_eq!(allocator.free_frames(), total - 1 - 16 - 2);
+
+        assert_eq!(allocator.free_owner(100), 17);
+        assert_eq!(allocator.owner(range), None);
+        allocator.free(other);
+        assert_eq!(allocator.free_frames(), total);
+        // everything was merged again
+        assert_eq!(allocator.free_len, 1);
+    }
+
+    #[test]
+    fn test_alloc_exhausted() {
+        const MEMORY: &[SimulatorMemDesc] = &[mem(0x10_0000, 0x2000, 1)];
+        let mut allocator = allocator(MEMORY);
+        assert!(allocator.alloc_order(2, 1).is_none());
+        assert!(allocator.alloc_order(1, 1).is_some());
+        assert!(allocator.alloc_order(0, 1).is_none());
+    }
+}
@@ -0,0 +1,361 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Module for the global heap allocator of the roottask. See [`FreeList`].
+//!
+//! The heap starts with a static arena in `.data`, because `link.ld` merges `.bss` into
+//! `.data` and there is no other memory before the frame allocator is initialized. When the
+//! arena is exhausted, the heap grows by fresh frames from [`frame_alloc`] that are mapped
+//! by [`vspace`]. Memory is never given back to the frame allocator. The heap doesn't hold
+//! its lock while it grows, because the frame allocator, [`vspace`], and the logger may
+//! allocate themselves.
+
+use crate::sync::SpinLock;
+use crate::{frame_alloc, vspace};
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::UnsafeCell;
+use core::mem::size_of;
+use core::ptr::{self, NonNull};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use libhedron::capability::MemCapPermissions;
+use libhedron::pd_ctrl::DelegateFlags;
+use libhedron::{PAGE_SIZE, ROOTTASK_CAPSEL};
+
+/// Size of the static arena that is available from the beginning.
+const ARENA_SIZE: usize = 0x1_0000;
+/// The heap grows by at least `2^GROW_ORDER` pages at once.
+const GROW_ORDER: u8 = 4;
+/// Minimum size and alignment of all blocks. A free block must be able to hold a
+/// [`FreeBlock`].
+const BLOCK_ALIGN: usize = 16;
+
+/// The static arena for the first allocations.
+#[repr(C, align(4096))]
+struct Arena(UnsafeCell<[u8; ARENA_SIZE]>);
+// The arena is handed to the heap only once, see `ARENA_TAKEN`.
+unsafe impl Sync for Arena {}
+static ARENA: Arena = Arena(UnsafeCell::new([0; ARENA_SIZE]));
+/// Whether the static arena was already given to the heap.
+static ARENA_TAKEN: AtomicBool = AtomicBool::new(false);
+/// Bytes that the heap got from the frame allocator.
+static GROWN: AtomicUsize = AtomicUsize::new(0);
+
+/// The global allocator of the roottask. Tests use the allocator of the host instead.
+#[cfg_attr(not(test), global_allocator)]
+#[cfg_attr(test, allow(dead_code))]
+static HEAP: Heap = Heap::new(fresh_memory);
+
+/// Returns fresh memory of at least the given size as start address and size. The memory
+/// must be valid, writable, and unused, and it must live forever.
+type MemorySource = fn(usize) -> Option<(usize, usize)>;
+
+/// Heap that is shared between all ECs of the roottask, possibly on different CPUs.
+struct Heap {
+    free_list: SpinLock<FreeList>,
+    source: MemorySource,
+}
+
+impl Heap {
+    /// Creates an empty heap that grows with memory from `source`.
+    const fn new(source: MemorySource) -> Self {
+        Self {
+            free_list: SpinLock::new(FreeList::new()),
+            source,
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for Heap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        loop {
+            if let Some(ptr) = self.free_list.lock().alloc(layout) {
+                return ptr.as_ptr();
+            }
+            // the lock is released here, so that the source may allocate itself
+            match (self.source)(FreeList::block_size(layout) + layout.align()) {
+                Some((start, size)) => self.free_list.lock().add_region(start, size),
+                None => return ptr::null_mut(),
+            }
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.free_list.lock().dealloc(ptr, layout)
+    }
+}
+
+/// The [`MemorySource`] of the global heap. Hands out the static arena first. After that,
+/// it maps fresh frames that are big enough for `size` bytes.
+fn fresh_memory(size: usize) -> Option<(usize, usize)> {
+    if !ARENA_TAKEN.swap(true, Ordering::SeqCst) {
+        return Some((ARENA.0.get() as usize, ARENA_SIZE));
+    }
+
+    let pages = (size as u64 + PAGE_SIZE - 1) / PAGE_SIZE;
+    let order = (64 - (pages - 1).leading_zeros()).max(GROW_ORDER as u32) as u8;
+    let len = PAGE_SIZE << order;
+
+    let phys = frame_alloc::alloc_order(order, ROOTTASK_CAPSEL)?;
+    let virt = match vspace::map(
+        phys,
+        len,
+        MemCapPermissions::READ | MemCapPermissions::WRITE,
+        DelegateFlags::builder().hypervisor(true).build(),
+    ) {
+        Ok(virt) => virt,
+        Err(e) => {
+            log::error!("heap: mapping {len:#x} bytes at {phys:#x} failed: {e:?}");
+            frame_alloc::free(phys);
+            return None;
+        }
+    };
+    GROWN.fetch_add(len as usize, Ordering::SeqCst);
+    Some((virt as usize, len as usize))
+}
+
+/// Returns the number of bytes that the heap got from the frame allocator.
+pub fn grown_bytes() -> usize {
+    GROWN.load(Ordering::SeqCst)
+}
+
+/// Called when an allocation fails, even after growing the heap.
+#[cfg(not(test))]
+#[alloc_error_handler]
+fn alloc_error_handler(layout: Layout) -> ! {
+    log::error!(
+        "heap: out of memory: allocation of {} bytes with alignment {} failed",
+        layout.size(),
+        layout.align()
+    );
+    panic!("out of memory");
+}
+
+/// Header of a free block. It lives at the beginning of the free memory itself.
+struct FreeBlock {
+    size: usize,
+    next: Option<NonNull<FreeBlock>>,
+}
+
+/// First-fit allocator that keeps the free blocks in a list, sorted by address, so that
+/// neighbours can be merged on deallocation.
+pub struct FreeList {
+    head: Option<NonNull<FreeBlock>>,
+}
+
+// The list only points into memory that belongs to the heap.
+unsafe impl Send for FreeList {}
+
+impl FreeList {
+    /// Creates an empty list without any memory.
+    pub const fn new() -> Self {
+        Self { head: None }
+    }
+
+    /// Adds the memory region to the free memory. Parts of it that are not aligned to
+    /// [`BLOCK_ALIGN`] are not used.
+    ///
+    /// # Safety
+    /// The region must be valid, writable, and unused memory that lives forever.
+    pub unsafe fn add_region(&mut self, start: usize, size: usize) {
+        let aligned_start = (start + BLOCK_ALIGN - 1) & !(BLOCK_ALIGN - 1);
+        let end = (start + size) & !(BLOCK_ALIGN - 1);
+        if end > aligned_start {
+            self.insert(aligned_start, end - aligned_start);
+        }
+    }
+
+    /// Returns a block for the layout from the first free block that fits.
+    pub fn alloc(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        let size = Self::block_size(layout);
+        let align = layout.align().max(BLOCK_ALIGN);
+
+        let mut prev: Option<NonNull<FreeBlock>> = None;
+        let mut current = self.head;
+        while let Some(block) = current {
+            let block_start = block.as_ptr() as usize;
+            let (block_size, next) = unsafe { (block.as_ref().size, block.as_ref().next) };
+            let block_end = block_start + block_size;
+            let start = (block_start + align - 1) & !(align - 1);
+            if start + size <= block_end {
+                // unlink the block and give the unused parts back
+                match prev {
+                    Some(mut prev) => unsafe { prev.as_mut().next = next },
+                    None => self.head = next,
+                }
+                unsafe {
+                    if start > block_start {
+                        self.insert(block_start, start - block_start);
+                    }
+                    if block_end > start + size {
+                        self.insert(start + size, block_end - start - size);
+                    }
+                }
+                return NonNull::new(start as *mut u8);
+            }
+            prev = current;
+            current = next;
+        }
+        None
+    }
+
+    /// Gives a block back that was returned by [`Self::alloc`] with the same layout.
+    ///
+    /// # Safety
+    /// The block must come from this list and must not be used anymore.
+    pub unsafe fn dealloc(&mut self, ptr: *mut u8, layout: Layout) {
+        self.insert(ptr as usize, Self::block_size(layout));
+    }
+
+    /// Returns the number of free bytes.
+    #[allow(dead_code)]
+    pub fn free_bytes(&self) -> usize {
+        let mut bytes = 0;
+        let mut current = self.head;
+        while let Some(block) = current {
+            let block = unsafe { block.as_ref() };
+            bytes += block.size;
+            current = block.next;
+        }
+        bytes
+    }
+
+    /// The number of bytes that a block for the layout occupies.
+    fn block_size(layout: Layout) -> usize {
+        (layout.size().max(size_of::<FreeBlock>()) + BLOCK_ALIGN - 1) & !(BLOCK_ALIGN - 1)
+    }
+
+    /// Inserts the free memory into the list and merges it with its neighbours.
+    ///
+    /// # Safety
+    /// The memory must be unused and aligned to [`BLOCK_ALIGN`].
+    unsafe fn insert(&mut self, start: usize, size: usize) {
+        let mut prev: Option<NonNull<FreeBlock>> = None;
+        let mut next = self.head;
+        while let Some(block) = next {
+            if block.as_ptr() as usize > start {
+                break;
+            }
+            prev = next;
+            next = block.as_ref().next;
+        }
+
+        let mut block = NonNull::new_unchecked(start as *mut FreeBlock);
+        block.as_ptr().write(FreeBlock { size, next });
+        if let Some(next) = next {
+            assert!(start + size <= next.as_ptr() as usize, "double free");
+            if start + size == next.as_ptr() as usize {
+                block.as_mut().size += next.as_ref().size;
+                block.as_mut().next = next.as_ref().next;
+            }
+        }
+        match prev {
+            Some(mut prev) => {
+                let prev_end = prev.as_ptr() as usize + prev.as_ref().size;
+                assert!(prev_end <= start, "double free");
+                if prev_end == start {
+                    prev.as_mut().size += block.as_ref().size;
+                    prev.as_mut().next = block.as_ref().next;
+                } else {
+                    prev.as_mut().next = Some(block);
+                }
+            }
+            None => self.head = Some(block),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Backing memory for a [`FreeList`] in tests.
+    #[repr(C, align(4096))]
+    struct Memory([u8; 0x2000]);
+
+    fn free_list(memory: &mut Memory) -> FreeList {
+        let mut free_list = FreeList::new();
+        unsafe { free_list.add_region(memory.0.as_mut_ptr() as usize, memory.0.len()) };
+        free_list
+    }
+
+    #[test]
+    fn test_alloc_respects_alignment() {
+        let mut memory = Box::new(Memory([0; 0x2000]));
+        let mut free_list = free_list(&mut memory);
+        let small = free_list
+            .alloc(Layout::from_size_align(3, 1).unwrap())
+            .unwrap();
+        let aligned = free_list
+            .alloc(Layout::from_size_align(64, 1024).unwrap())
+            .unwrap();
+        assert_eq!(small.as_ptr() as usize % BLOCK_ALIGN, 0);
+        assert_eq!(aligned.as_ptr() as usize % 1024, 0);
+        assert_eq!(free_list.free_bytes(), 0x2000 - 16 - 64);
+    }
+
+    #[test]
+    fn test_dealloc_merges_blocks() {
+        let mut memory = Box::new(Memory([0; 0x2000]));
+        let mut free_list = free_list(&mut memory);
+        let layout = Layout::from_size_align(0x800, 8).unwrap();
+        let blocks = [(); 4].map(|_| free_list.alloc(layout).unwrap());
+        assert_eq!(free_list.free_bytes(), 0);
+        assert!(free_list.alloc(Layout::new::<u8>()).is_none());
+
+        // free in an order that requires merging with both neighbours
+        for i in [0, 2, 1, 3] {
+            unsafe { free_list.dealloc(blocks[i].as_ptr(), layout) };
+        }
+        assert_eq!(free_list.free_bytes(), 0x2000);
+        assert!(free_list.alloc(Layout::new::<Memory>()).is_some());
+    }
+
+    /// [`MemorySource`] that leaks page-aligned memory from the host allocator.
+    fn fake_frames(size: usize) -> Option<(usize, usize)> {
+        let size = (size + PAGE_SIZE as usize - 1) & !(PAGE_SIZE as usize - 1);
+        let layout = Layout::from_size_align(size, PAGE_SIZE as usize).unwrap();
+        Some((unsafe { alloc::alloc::alloc(layout) } as usize, size))
+    }
+
+    #[test]
+    fn test_heap_grows_from_source() {
+        let heap = Heap::new(fake_frames);
+        let layout = Layout::from_size_align(0x1800, 0x1000).unwrap();
+        let blocks = [(); 3].map(|_| unsafe { heap.alloc(layout) });
+        for block in blocks {
+            assert!(!block.is_null());
+            assert_eq!(block as usize % 0x1000, 0);
+            unsafe { block.write_bytes(0xff, layout.size()) };
+        }
+        for block in blocks {
+            unsafe { heap.dealloc(block, layout) };
+        }
+        assert!(heap.free_list.lock().free_bytes() >= 3 * layout.size());
+    }
+
+    #[test]
+    fn test_heap_without_memory() {
+        let heap = Heap::new(|_| None);
+        assert!(unsafe { heap.alloc(Layout::new::<u64>()) }.is_null());
+    }
+}
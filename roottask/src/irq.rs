@@ -0,0 +1,100 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Module for interrupt-driven drivers. See [`Irq`].
+
+use crate::capsel_alloc;
+use crate::kobject::Sm;
+use libhedron::assign_gsi::{assign_gsi, gsi_sm_kernel_sel, MsiInfo};
+use libhedron::capability::{CrdObjSM, SMCapPermissions};
+use libhedron::error::HedronError;
+use libhedron::hip::Hip;
+use libhedron::pd_ctrl::{pd_ctrl_delegate, DelegateFlags};
+use libhedron::syscall::SyscallStatus;
+use libhedron::ROOTTASK_CAPSEL;
+
+/// A global system interrupt (GSI) that is routed to a CPU. Each interrupt performs an `UP`
+/// operation on the interrupt semaphore. A driver EC waits for interrupts with [`Irq::wait`].
+#[derive(Debug)]
+pub struct Irq {
+    gsi: u64,
+    sm: Sm,
+    msi: MsiInfo,
+}
+
+impl Irq {
+    /// Obtains the interrupt semaphore of the GSI from the hypervisor at a newly allocated
+    /// capability selector of the roottask and routes the GSI to the given CPU.
+    ///
+    /// # Parameters
+    /// - `gsi` The global system interrupt.
+    /// - `cpu` The CPU that receives the interrupt. The EC that waits should run there.
+    /// - `dev_cfg_addr` For MSIs, the virtual address of the mapped PCI configuration space of
+    ///   the device. `None` for IOAPIC pins.
+    pub fn new(
+        hip: &Hip,
+        gsi: u64,
+        cpu: u16,
+        dev_cfg_addr: Option<u64>,
+    ) -> Result<Self, HedronError> {
+        let sm_sel = capsel_alloc::alloc().ok_or(HedronError::Syscall(SyscallStatus::BadCap))?;
+        let permissions = SMCapPermissions::UP | SMCapPermissions::DOWN;
+        // from here on, the handle revokes the semaphore and frees the selector on failure
+        let sm = unsafe { Sm::from_raw(sm_sel, permissions) };
+        pd_ctrl_delegate(
+            ROOTTASK_CAPSEL,
+            ROOTTASK_CAPSEL,
+            CrdObjSM::new(gsi_sm_kernel_sel(hip, gsi), 0, permissions),
+            CrdObjSM::new(sm_sel, 0, permissions),
+            // most important boolean flag: "use hypervisor as src"
+            DelegateFlags::builder().hypervisor(true).build(),
+        )?;
+        let msi = assign_gsi(sm_sel, dev_cfg_addr.unwrap_or(0), cpu)?;
+        Ok(Self { gsi, sm, msi })
+    }
+
+    /// Returns the GSI.
+    pub fn gsi(&self) -> u64 {
+        self.gsi
+    }
+
+    /// Returns the values that must be programmed into the MSI capability of the device.
+    /// Meaningless for IOAPIC pins.
+    #[allow(dead_code)]
+    pub fn msi(&self) -> MsiInfo {
+        self.msi
+    }
+
+    /// Blocks until at least one interrupt arrived since the last call. Multiple
+    /// interrupts that arrived in the meantime are consumed at once.
+    #[allow(dead_code)]
+    pub fn wait(&self) -> Result<(), HedronError> {
+        self.sm.down_zero()
+    }
+
+    /// Like [`Self::wait`] but gives up with [`SyscallStatus::Timeout`] when the TSC
+    /// reaches the deadline.
+    pub fn wait_until(&self, deadline: u64) -> Result<(), HedronError> {
+        self.sm.down_zero_until(deadline)
+    }
+}
@@ -0,0 +1,370 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Module with owning handles for kernel objects: [`Pd`], [`Ec`], [`Sc`], [`Pt`], and [`Sm`].
+//!
+//! Each handle owns a capability selector from [`crate::capsel_alloc`] and knows the
+//! permissions of its capability. Operations that the permissions don't allow fail with
+//! [`SyscallStatus::BadCap`] without a syscall. When a handle is dropped, the capability is
+//! revoked from the roottask and from all PDs it was delegated to, and the selector is freed.
+
+use crate::capsel_alloc;
+use core::time::Duration;
+use libhedron::capability::{
+    CapSel, CrdNull, CrdObjEC, CrdObjPD, CrdObjPT, CrdObjSC, CrdObjSM, ECCapPermissions,
+    PDCapPermissions, PTCapPermissions, SCCapPermissions, SMCapPermissions,
+};
+use libhedron::create_ec::{create_ec, EcKind};
+use libhedron::create_pd::create_pd;
+use libhedron::create_pt::create_pt;
+use libhedron::create_sc::{create_sc, Qpd};
+use libhedron::create_sm::create_sm;
+use libhedron::ec_ctrl::ec_ctrl_recall;
+use libhedron::error::HedronError;
+use libhedron::ipc::ipc_call;
+use libhedron::mtd::Mtd;
+use libhedron::pd_ctrl::{pd_ctrl_delegate, DelegateFlags};
+use libhedron::pt_ctrl::{pt_ctrl, PortalId};
+use libhedron::revoke::revoke;
+use libhedron::sc_ctrl::sc_ctrl;
+use libhedron::sm_ctrl::{sm_ctrl_down, sm_ctrl_up};
+use libhedron::syscall::SyscallStatus;
+use libhedron::ROOTTASK_CAPSEL;
+
+/// Allocates a capability selector or fails with [`SyscallStatus::BadCap`].
+fn alloc_sel() -> Result<CapSel, HedronError> {
+    capsel_alloc::alloc().ok_or(HedronError::Syscall(SyscallStatus::BadCap))
+}
+
+/// Runs the syscall that creates a kernel object at a freshly allocated selector. Frees the
+/// selector again, if the syscall fails.
+fn create_at_new_sel(
+    create: impl FnOnce(CapSel) -> Result<(), HedronError>,
+) -> Result<CapSel, HedronError> {
+    let sel = alloc_sel()?;
+    if let Err(e) = create(sel) {
+        capsel_alloc::free(sel);
+        return Err(e);
+    }
+    Ok(sel)
+}
+
+/// Implements the common functionality of all handles: getters, delegation, and the
+/// revocation on drop. Not every kind of handle uses all of it yet.
+macro_rules! impl_kobject {
+    ($name: ident, $crd: ident, $permissions: ident) => {
+        #[allow(dead_code)]
+        impl $name {
+            /// Takes ownership of a capability selector that was allocated from
+            /// [`crate::capsel_alloc`] and already refers to a kernel object of this kind,
+            /// for example after a delegation from the hypervisor.
+            ///
+            /// # Safety
+            /// The selector must refer to a kernel object of this kind with the given
+            /// permissions and must not be owned by another handle.
+            pub unsafe fn from_raw(sel: CapSel, permissions: $permissions) -> Self {
+                Self { sel, permissions }
+            }
+
+            /// Returns the capability selector in the capability space of the roottask.
+            pub fn sel(&self) -> CapSel {
+                self.sel
+            }
+
+            /// Returns the permissions of the capability.
+            pub fn permissions(&self) -> $permissions {
+                self.permissions
+            }
+
+            /// Returns an error, if the capability lacks one of the permissions.
+            fn require(&self, permissions: $permissions) -> Result<(), HedronError> {
+                if self.permissions.contains(permissions) {
+                    Ok(())
+                } else {
+                    Err(HedronError::Syscall(SyscallStatus::BadCap))
+                }
+            }
+
+            /// Delegates the capability with the given (possibly reduced) permissions to the
+            /// capability selector `dest_sel` of the PD `dest_pd`. The delegation is revoked
+            /// when this handle is dropped.
+            pub fn delegate_to(
+                &self,
+                dest_pd: CapSel,
+                dest_sel: CapSel,
+                permissions: $permissions,
+            ) -> Result<(), HedronError> {
+                self.require(permissions)?;
+                pd_ctrl_delegate(
+                    ROOTTASK_CAPSEL,
+                    dest_pd,
+                    $crd::new(self.sel, 0, permissions),
+                    $crd::new(dest_sel, 0, permissions),
+                    DelegateFlags::default(),
+                )
+            }
+        }
+
+        impl Drop for $name {
+            fn drop(&mut self) {
+                let res = revoke($crd::new(self.sel, 0, $permissions::all()), true, None);
+                if let Err(e) = res {
+                    log::warn!(
+                        "failed to revoke {} at capability selector {}: {:?}",
+                        stringify!($name),
+                        self.sel,
+                        e
+                    );
+                }
+                capsel_alloc::free(self.sel);
+            }
+        }
+    };
+}
+
+/// Owning handle for a protection domain (PD).
+#[derive(Debug)]
+pub struct Pd {
+    sel: CapSel,
+    permissions: PDCapPermissions,
+}
+
+impl Pd {
+    /// Creates a new PD that is a child of the roottask.
+    pub fn create() -> Result<Self, HedronError> {
+        let sel = create_at_new_sel(|sel| create_pd(sel, ROOTTASK_CAPSEL, CrdNull::new()))?;
+        Ok(Self {
+            sel,
+            permissions: PDCapPermissions::all(),
+        })
+    }
+}
+
+impl_kobject!(Pd, CrdObjPD, PDCapPermissions);
+
+/// Owning handle for an execution context (EC).
+#[derive(Debug)]
+pub struct Ec {
+    sel: CapSel,
+    permissions: ECCapPermissions,
+}
+
+impl Ec {
+    /// Creates a new EC. See [`create_ec`] for the parameters.
+    pub fn create(
+        owner_pd: CapSel,
+        kind: EcKind,
+        cpu: u16,
+        utcb_addr: u64,
+        stack_ptr: u64,
+        evt_base: CapSel,
+    ) -> Result<Self, HedronError> {
+        let sel = create_at_new_sel(|sel| {
+            create_ec(sel, owner_pd, kind, cpu, utcb_addr, stack_ptr, evt_base)
+        })?;
+        Ok(Self {
+            sel,
+            permissions: ECCapPermissions::all(),
+        })
+    }
+
+    /// Interrupts the execution of the EC. See [`ec_ctrl_recall`].
+    #[allow(dead_code)]
+    pub fn recall(&self) -> Result<(), HedronError> {
+        self.require(ECCapPermissions::EC_CTRL)?;
+        ec_ctrl_recall(self.sel)
+    }
+}
+
+impl_kobject!(Ec, CrdObjEC, ECCapPermissions);
+
+/// Owning handle for a scheduling context (SC).
+#[derive(Debug)]
+pub struct Sc {
+    sel: CapSel,
+    permissions: SCCapPermissions,
+}
+
+impl Sc {
+    /// Creates a new SC for the global EC. The EC starts running right away.
+    pub fn create(owner_pd: CapSel, ec: &Ec, qpd: Qpd) -> Result<Self, HedronError> {
+        ec.require(ECCapPermissions::CREATE_SC)?;
+        let sel = create_at_new_sel(|sel| create_sc(sel, owner_pd, ec.sel(), qpd))?;
+        Ok(Self {
+            sel,
+            permissions: SCCapPermissions::all(),
+        })
+    }
+
+    /// Returns the CPU time that the SC consumed so far. See [`sc_ctrl`].
+    #[allow(dead_code)]
+    pub fn consumed_time(&self) -> Result<Duration, HedronError> {
+        self.require(SCCapPermissions::SC_CTRL)?;
+        sc_ctrl(self.sel)
+    }
+}
+
+impl_kobject!(Sc, CrdObjSC, SCCapPermissions);
+
+/// Owning handle for a portal (PT).
+#[derive(Debug)]
+pub struct Pt {
+    sel: CapSel,
+    permissions: PTCapPermissions,
+}
+
+impl Pt {
+    /// Creates a new portal that is handled by the local EC. See [`create_pt`].
+    pub fn create(owner_pd: CapSel, ec: &Ec, mtd: Mtd, entry_ip: u64) -> Result<Self, HedronError> {
+        ec.require(ECCapPermissions::CREATE_PT)?;
+        let sel = create_at_new_sel(|sel| create_pt(sel, owner_pd, ec.sel(), mtd, entry_ip))?;
+        Ok(Self {
+            sel,
+            permissions: PTCapPermissions::all(),
+        })
+    }
+
+    /// Sets the portal ID. See [`pt_ctrl`].
+    pub fn set_id(&self, id: PortalId) -> Result<(), HedronError> {
+        self.require(PTCapPermissions::PT_CTRL)?;
+        pt_ctrl(self.sel, id)
+    }
+
+    /// Calls the portal with the message in the UTCB of the current EC. See [`ipc_call`].
+    pub fn call(&self) -> Result<(), HedronError> {
+        self.require(PTCapPermissions::CALL)?;
+        ipc_call(self.sel)
+    }
+}
+
+impl_kobject!(Pt, CrdObjPT, PTCapPermissions);
+
+/// Owning handle for a semaphore (SM). Semaphores are the basic blocking primitive of Hedron.
+/// They are used for synchronisation between ECs, to wait for interrupts, and to sleep until
+/// a TSC deadline.
+#[derive(Debug)]
+pub struct Sm {
+    sel: CapSel,
+    permissions: SMCapPermissions,
+}
+
+impl Sm {
+    /// Creates a new semaphore with the given counter value.
+    pub fn create(initial_count: u64) -> Result<Self, HedronError> {
+        let sel = create_at_new_sel(|sel| create_sm(sel, ROOTTASK_CAPSEL, initial_count))?;
+        Ok(Self {
+            sel,
+            permissions: SMCapPermissions::all(),
+        })
+    }
+
+    /// Increments the counter or wakes up a blocked EC.
+    #[allow(dead_code)]
+    pub fn up(&self) -> Result<(), HedronError> {
+        self.require(SMCapPermissions::UP)?;
+        sm_ctrl_up(self.sel)
+    }
+
+    /// Decrements the counter. Blocks, if the counter is zero.
+    pub fn down(&self) -> Result<(), HedronError> {
+        self.require(SMCapPermissions::DOWN)?;
+        sm_ctrl_down(self.sel, false, None)
+    }
+
+    /// Sets the counter to zero. Blocks, if the counter already is zero. This consumes
+    /// all pending `UP` operations at once.
+    pub fn down_zero(&self) -> Result<(), HedronError> {
+        self.require(SMCapPermissions::DOWN)?;
+        sm_ctrl_down(self.sel, true, None)
+    }
+
+    /// Like [`Self::down`] but returns [`SyscallStatus::Timeout`] if the counter is still
+    /// zero when the TSC reaches `deadline`.
+    pub fn down_until(&self, deadline: u64) -> Result<(), HedronError> {
+        self.require(SMCapPermissions::DOWN)?;
+        sm_ctrl_down(self.sel, false, Some(deadline))
+    }
+
+    /// Like [`Self::down_zero`] but returns [`SyscallStatus::Timeout`] if the counter is
+    /// still zero when the TSC reaches `deadline`.
+    pub fn down_zero_until(&self, deadline: u64) -> Result<(), HedronError> {
+        self.require(SMCapPermissions::DOWN)?;
+        sm_ctrl_down(self.sel, true, Some(deadline))
+    }
+}
+
+impl_kobject!(Sm, CrdObjSM, SMCapPermissions);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::simulator::{self, SimulatorConfig};
+
+    /// One test for all handles, because they share the global capability selector allocator.
+    #[test]
+    fn test_handles_revoke_on_drop() {
+        simulator::reset(SimulatorConfig::default());
+        capsel_alloc::init(simulator::with(|sim| sim.hip()));
+
+        let sm = Sm::create(1).unwrap();
+        let sm_sel = sm.sel();
+        sm.down().unwrap();
+        assert_eq!(
+            sm.down_until(1),
+            Err(HedronError::Syscall(SyscallStatus::Timeout))
+        );
+        sm.up().unwrap();
+        simulator::with(|sim| assert_eq!(sim.sm_count(sm_sel), Some(1)));
+
+        // the delegated capability only allows UP
+        let pd = Pd::create().unwrap();
+        sm.delegate_to(pd.sel(), 7, SMCapPermissions::UP).unwrap();
+        simulator::with(|sim| {
+            assert_eq!(
+                sim.object_permissions(pd.sel(), 7),
+                Some(SMCapPermissions::UP.bits())
+            )
+        });
+        let up_only = unsafe { Sm::from_raw(sm_sel, SMCapPermissions::UP) };
+        assert_eq!(
+            up_only.down(),
+            Err(HedronError::Syscall(SyscallStatus::BadCap))
+        );
+        core::mem::forget(up_only);
+
+        drop(sm);
+        simulator::with(|sim| {
+            assert_eq!(sim.object(sm_sel), None);
+            assert_eq!(sim.object_permissions(pd.sel(), 7), None);
+        });
+        // the selector is free again
+        assert_eq!(capsel_alloc::alloc(), Some(sm_sel));
+        capsel_alloc::free(sm_sel);
+
+        let ec = Ec::create(ROOTTASK_CAPSEL, EcKind::Local, 0, 0x1000, 0x2000, 0).unwrap();
+        let pt = Pt::create(ROOTTASK_CAPSEL, &ec, Mtd::empty(), 0x1234).unwrap();
+        pt.set_id(42).unwrap();
+        // a local EC can't have an SC
+        assert!(Sc::create(ROOTTASK_CAPSEL, &ec, Qpd::default()).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Module for starting child PDs from ELF executables in boot modules. See [`load`].
+//!
+//! Every `PT_LOAD` segment gets fresh frames from [`frame_alloc`], owned by the child PD.
+//! The roottask maps them temporarily to copy the file content and to zero the rest, which
+//! includes the `.bss` part where the memory size exceeds the file size. Then they are
+//! delegated to the child with the permissions of the segment.
+//!
+//! The child starts with one global EC. Its events are handled by [`ExceptionPortals`]. For
+//! the startup event, the handler sets the entry point and the stack pointer. Any other
+//! event is logged and kills the child.
+
+use crate::boot_modules::BootModule;
+use crate::elf::{ElfError, ElfFile, LoadSegment};
+use crate::exception::{
+    log_exception, ExceptionAction, ExceptionHandler, ExceptionPortals, ExceptionPortalsError,
+};
+use crate::frame_alloc;
+use crate::kobject::{Ec, Pd, Sc};
+use crate::vspace::{self, MapError};
+use alloc::string::String;
+use alloc::sync::Arc;
+use libhedron::capability::{CapSel, MemCapPermissions};
+use libhedron::create_ec::EcKind;
+use libhedron::create_sc::Qpd;
+use libhedron::delegate::{delegate_mem_range, RangeDelegationError};
+use libhedron::error::HedronError;
+use libhedron::event::Event;
+use libhedron::mtd::Mtd;
+use libhedron::pd_ctrl::DelegateFlags;
+use libhedron::{PAGE_SIZE, ROOTTASK_CAPSEL};
+
+/// Initial stack pointer of a child. The stack grows downwards from here.
+const CHILD_STACK_TOP: u64 = 0x7fff_f000_0000;
+/// The stack of a child has `2^CHILD_STACK_ORDER` pages.
+const CHILD_STACK_ORDER: u8 = 4;
+/// Virtual address of the UTCB of the global EC of a child.
+const CHILD_UTCB_ADDR: u64 = 0x7fff_ffff_e000;
+/// Event base of the global EC of a child.
+const CHILD_EVT_BASE: CapSel = 0;
+/// Frames are allocated in chunks of at most `2^MAX_CHUNK_ORDER` pages.
+const MAX_CHUNK_ORDER: u32 = 10;
+
+/// Errors of [`load`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    /// The boot module is empty.
+    EmptyModule,
+    /// The module is no valid executable.
+    Elf(ElfError),
+    /// The segment overlaps with another segment, the stack, or the UTCB of the child.
+    InvalidLayout(LoadSegment),
+    /// There are not enough free frames.
+    OutOfMemory,
+    /// Mapping the module or fresh frames into the roottask failed.
+    Map(MapError),
+    /// Delegating frames to the child failed.
+    Delegation(RangeDelegationError),
+    /// Creating a kernel object of the child failed.
+    Hedron(HedronError),
+}
+
+impl From<ElfError> for LoadError {
+    fn from(e: ElfError) -> Self {
+        Self::Elf(e)
+    }
+}
+
+impl From<MapError> for LoadError {
+    fn from(e: MapError) -> Self {
+        Self::Map(e)
+    }
+}
+
+impl From<RangeDelegationError> for LoadError {
+    fn from(e: RangeDelegationError) -> Self {
+        Self::Delegation(e)
+    }
+}
+
+impl From<HedronError> for LoadError {
+    fn from(e: HedronError) -> Self {
+        Self::Hedron(e)
+    }
+}
+
+impl From<ExceptionPortalsError> for LoadError {
+    fn from(e: ExceptionPortalsError) -> Self {
+        match e {
+            ExceptionPortalsError::OutOfVirtualMemory => Self::Map(MapError::OutOfVirtualMemory),
+            ExceptionPortalsError::Hedron(e) => Self::Hedron(e),
+        }
+    }
+}
+
+/// A running child PD. Dropping it destroys the PD and frees its memory.
+#[derive(Debug)]
+pub struct Child {
+    // The order of the fields is the order in which they are dropped.
+    #[allow(dead_code)]
+    sc: Sc,
+    ec: Ec,
+    exceptions: ExceptionPortals,
+    pd: Pd,
+    #[allow(dead_code)]
+    frames: OwnedFrames,
+}
+
+impl Child {
+    /// Returns the PD of the child.
+    pub fn pd(&self) -> &Pd {
+        &self.pd
+    }
+
+    /// Returns the global EC of the child.
+    pub fn ec(&self) -> &Ec {
+        &self.ec
+    }
+
+    /// Returns true, if the child was killed because of an exception.
+    pub fn killed(&self) -> bool {
+        self.exceptions.killed()
+    }
+}
+
+/// Frees all frames of a PD on drop.
+#[derive(Debug)]
+struct OwnedFrames(CapSel);
+
+impl Drop for OwnedFrames {
+    fn drop(&mut self) {
+        frame_alloc::free_owner(self.0);
+    }
+}
+
+/// Creates a new PD from the ELF executable in the boot module and starts it.
+pub fn load(module: &BootModule) -> Result<Child, LoadError> {
+    if module.size() == 0 {
+        return Err(LoadError::EmptyModule);
+    }
+    let mapping = module.map()?;
+    let elf = ElfFile::parse(mapping.as_slice())?;
+
+    // Declared before the PD, so that the frames are freed after the PD is revoked, if
+    // loading fails.
+    let frames;
+    let pd = Pd::create()?;
+    frames = OwnedFrames(pd.sel());
+
+    let stack_pages = 1 << CHILD_STACK_ORDER;
+    let stack_bottom = CHILD_STACK_TOP - stack_pages * PAGE_SIZE;
+    let mut next_free_page = 0;
+    for segment in elf.load_segments() {
+        let first_page = segment.vaddr / PAGE_SIZE;
+        let end_page = (segment.vaddr + segment.mem_size + PAGE_SIZE - 1) / PAGE_SIZE;
+        // segments are sorted by address and must not share pages
+        if first_page < next_free_page || end_page > stack_bottom / PAGE_SIZE {
+            return Err(LoadError::InvalidLayout(segment));
+        }
+        next_free_page = end_page;
+
+        let data = elf.segment_data(&segment);
+        let data_offset = segment.vaddr % PAGE_SIZE;
+        populate(
+            &pd,
+            first_page,
+            end_page - first_page,
+            segment.permissions(),
+            |offset, chunk| {
+                // the part of the file content that belongs to the chunk
+                let start = offset.max(data_offset);
+                let end = (offset + chunk.len() as u64).min(data_offset + data.len() as u64);
+                if start < end {
+                    chunk[(start - offset) as usize..(end - offset) as usize].copy_from_slice(
+                        &data[(start - data_offset) as usize..(end - data_offset) as usize],
+                    );
+                }
+            },
+        )?;
+    }
+    populate(
+        &pd,
+        stack_bottom / PAGE_SIZE,
+        stack_pages,
+        MemCapPermissions::READ | MemCapPermissions::WRITE,
+        |_, _| {},
+    )?;
+
+    let entry = elf.entry();
+    let name = String::from(module.name());
+    let handler: Arc<ExceptionHandler> = Arc::new(move |event, state| match event {
+        Event::Startup => {
+            state.rip = entry;
+            state.rsp = CHILD_STACK_TOP;
+            state.set_mtd(Mtd::RIP_LEN | Mtd::RSP);
+            ExceptionAction::Resume
+        }
+        event => {
+            log_exception(&name, event, state);
+            ExceptionAction::Kill
+        }
+    });
+    let exceptions = ExceptionPortals::create(module.name(), pd.sel(), CHILD_EVT_BASE, 0, handler)?;
+
+    let ec = Ec::create(
+        pd.sel(),
+        EcKind::Global,
+        0,
+        CHILD_UTCB_ADDR,
+        CHILD_STACK_TOP,
+        CHILD_EVT_BASE,
+    )?;
+    // the SC starts the child
+    let sc = Sc::create(pd.sel(), &ec, Qpd::default())?;
+    Ok(Child {
+        sc,
+        ec,
+        exceptions,
+        pd,
+        frames,
+    })
+}
+
+/// Allocates and zeroes frames for `count` pages of the child, starting at the page number
+/// `first_page`. `init` can fill each chunk of frames before it is delegated to the child.
+/// It gets the byte offset of the chunk relative to `first_page`.
+fn populate(
+    pd: &Pd,
+    first_page: u64,
+    count: u64,
+    permissions: MemCapPermissions,
+    mut init: impl FnMut(u64, &mut [u8]),
+) -> Result<(), LoadError> {
+    let end_page = first_page + count;
+    let mut page = first_page;
+    while page < end_page {
+        // as big as possible but aligned, so that a single delegation is enough
+        let remaining = end_page - page;
+        let order = page
+            .trailing_zeros()
+            .min(63 - remaining.leading_zeros())
+            .min(MAX_CHUNK_ORDER) as u8;
+        let phys = frame_alloc::alloc_order(order, pd.sel()).ok_or(LoadError::OutOfMemory)?;
+        let len = PAGE_SIZE << order;
+
+        let virt = vspace::map(
+            phys,
+            len,
+            MemCapPermissions::READ | MemCapPermissions::WRITE,
+            DelegateFlags::builder().hypervisor(true).build(),
+        )?;
+        let chunk = unsafe { core::slice::from_raw_parts_mut(virt as *mut u8, len as usize) };
+        chunk.fill(0);
+        init((page - first_page) * PAGE_SIZE, chunk);
+        vspace::unmap(virt);
+
+        delegate_mem_range(
+            ROOTTASK_CAPSEL,
+            pd.sel(),
+            phys / PAGE_SIZE,
+            page,
+            1 << order,
+            permissions,
+            DelegateFlags::builder().hypervisor(true).build(),
+        )?;
+        page += 1 << order;
+    }
+    Ok(())
+}
@@ -24,6 +24,7 @@ SOFTWARE.
 //! Module to enable a [log]-compatible logger that uses the serial device and
 //! QEMUs debugcon device.
 
+use crate::bda::BiosDataArea;
 use crate::debugcon::{get_debugcon_port, DebugconPort};
 use crate::serial::get_serial_port;
 use core::fmt::Write;
@@ -34,8 +35,9 @@ use uart_16550::SerialPort;
 static mut LOGGER: LoggerFacade = LoggerFacade(None);
 
 /// Initializes the logger facade. Uses the serial device for logging and the QEMU debugcon logger.
-/// When this function returns, macros like `log::info!()` can be called.
-pub fn init(level: log::LevelFilter) {
+/// When this function returns, macros like `log::info!()` can be called. The serial port is
+/// taken from the [`BiosDataArea`], if it is available.
+pub fn init(level: log::LevelFilter, bios_data_area: Option<&BiosDataArea>) {
     let mut debugcon = runs_inside_qemu()
         .is_maybe_or_very_likely()
         .then(get_debugcon_port);
@@ -44,7 +46,7 @@ pub fn init(level: log::LevelFilter) {
         let _ = writeln!(debugcon, "debugcon logger initialized");
     }
 
-    let (mut serial, serial_port_num) = get_serial_port();
+    let (mut serial, serial_port_num) = get_serial_port(bios_data_area);
 
     let _ = writeln!(
         &mut serial,
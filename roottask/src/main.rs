@@ -22,28 +22,78 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
 
-#![no_std]
-#![no_main]
+// Host-side unit tests use the test harness of the standard library.
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
 #![feature(panic_info_message)]
+#![cfg_attr(not(test), feature(alloc_error_handler))]
 
+extern crate alloc;
+
+#[cfg(not(test))]
 core::arch::global_asm!(include_str!("start.S"));
 
 mod bda;
+mod boot_modules;
+mod capsel_alloc;
 mod debugcon;
-mod hedron;
+mod elf;
+mod exception;
+mod frame_alloc;
+mod heap;
+mod irq;
+mod kobject;
+mod loader;
 mod logger;
+mod power;
 mod serial;
+mod stack;
+mod sync;
+mod vspace;
+
+use crate::irq::Irq;
+use crate::kobject::{Ec, Pt, Sm};
+use core::cell::UnsafeCell;
+use core::mem::size_of;
+use core::sync::atomic::{AtomicU64, Ordering};
+use libhedron::create_ec::EcKind;
+use libhedron::hip::Hip;
+use libhedron::ipc::{ipc_reply, portal_stack_ptr, PortalEntry};
+use libhedron::mtd::Mtd;
+use libhedron::pd_ctrl::msr_read;
+use libhedron::pt_ctrl::PortalId;
+use libhedron::sc_ctrl::sc_ctrl;
+use libhedron::utcb::Utcb;
+use libhedron::{ROOTTASK_CAPSEL, ROOTTASK_SC_CAPSEL};
+
+/// Index of the IA32_APIC_BASE MSR.
+const IA32_APIC_BASE_MSR: u32 = 0x1b;
+/// GSI of the COM1 serial port on the IOAPIC.
+const COM1_GSI: u64 = 4;
 
-use crate::hedron::capability::CrdPortIO;
-use crate::hedron::pd_ctrl::{pd_ctrl_delegate, DelegateFlags};
-use crate::hedron::ROOTTASK_CAPSEL;
-use core::panic::PanicInfo;
-use core::sync::atomic::{compiler_fence, Ordering};
+/// Virtual address where Hedron places the UTCB of the local EC of the IPC demonstration.
+static DEMO_EC_UTCB_ADDR: AtomicU64 = AtomicU64::new(0);
+/// Portal ID of the portal of the IPC demonstration.
+const DEMO_PT_ID: PortalId = 0x1337;
+
+/// Stack for the local EC of the IPC demonstration.
+#[repr(C, align(4096))]
+struct DemoStack(UnsafeCell<[u8; 0x4000]>);
+// Only the local EC of the IPC demonstration uses the stack.
+unsafe impl Sync for DemoStack {}
+static DEMO_EC_STACK: DemoStack = DemoStack(UnsafeCell::new([0; 0x4000]));
 
 /// Minimal roottask that performs some calculations and prints to serial and QEMUs debugcon port.
 #[no_mangle]
-fn rust_entry(hip_ptr: *const u8, utcb_ptr: *const u8) -> ! {
-    logger::init(log::LevelFilter::max());
+fn rust_entry(hip_ptr: *const u8, utcb_ptr: *mut u8) -> ! {
+    stack::fill_canary();
+    // before the logger, because the serial port is found via the BDA
+    vspace::init(hip_ptr as u64, utcb_ptr as u64);
+    let bda = bda::get_bda();
+    logger::init(log::LevelFilter::max(), bda.as_ref().ok());
+    if let Err(e) = bda {
+        log::warn!("can't read the BDA, using the default serial port: {e:?}");
+    }
     // demonstration that vector instructions and vector registers work
     // => no #GPF or so due to stack misalignment
     let a = [1.1, 2.2, 3.3, 4.4];
@@ -56,16 +106,163 @@ fn rust_entry(hip_ptr: *const u8, utcb_ptr: *const u8) -> ! {
     log::info!("Hello World from Roottask: hip_ptr={hip_ptr:?}, utcb_ptr:{utcb_ptr:?}");
     log::info!("a[{a:?}] * b[{b:?}] = c[{c:?}]");
 
+    let hip = unsafe { Hip::from_ptr(hip_ptr) }.expect("HIP must be valid");
+    log::info!(
+        "HIP: api_version={}, cpus={}, tsc_freq={} kHz, cap_sels={}",
+        hip.api_version(),
+        hip.num_cpus(),
+        hip.tsc_freq_khz(),
+        hip.num_cap_sel()
+    );
+    for mem in hip.mem_descs() {
+        log::debug!("{mem:?}");
+    }
+
+    capsel_alloc::init(hip);
+    // from now on, faults of the roottask are logged
+    let _roottask_exceptions =
+        exception::protect_roottask().expect("exception portals of the roottask must be created");
+    frame_alloc::init(hip);
+    log::info!(
+        "free memory: {} KiB",
+        frame_alloc::free_frames() * libhedron::PAGE_SIZE / 1024
+    );
+
+    // demonstration that the heap grows beyond its static arena
+    let buf = alloc::vec![0_u8; 0x2_0000];
+    log::info!(
+        "heap: allocated {} bytes, heap grew by {} bytes",
+        buf.len(),
+        heap::grown_bytes()
+    );
+    drop(buf);
+
+    let mut children = alloc::vec::Vec::new();
+    // the first boot module is the roottask itself
+    for module in boot_modules::boot_modules(hip).skip(1) {
+        log::info!(
+            "boot module {:#x}..{:#x}: {:?}",
+            module.phys.start,
+            module.phys.end,
+            module.cmdline
+        );
+        match loader::load(&module) {
+            Ok(child) => children.push(child),
+            Err(e) => log::info!("not starting {:?}: {e:?}", module.name()),
+        }
+    }
+
+    let utcb = unsafe { Utcb::from_ptr(utcb_ptr) };
+    log::debug!("{utcb:?}");
+
+    // demonstration that semaphores and timeouts work
+    let sm = Sm::create(1).expect("semaphore must be created");
+    sm.down().expect("must not block, as the counter is 1");
+    // 1 ms, as the TSC frequency is in kHz
+    let deadline = unsafe { core::arch::x86_64::_rdtsc() } + hip.tsc_freq_khz() as u64;
+    let res = sm.down_until(deadline);
+    log::info!("semaphore down with a timeout of 1ms: {res:?}");
+
+    // demonstration that interrupts arrive at a semaphore
+    match Irq::new(hip, COM1_GSI, 0, None) {
+        Ok(irq) => {
+            let deadline = unsafe { core::arch::x86_64::_rdtsc() } + hip.tsc_freq_khz() as u64;
+            let res = irq.wait_until(deadline);
+            log::info!("waiting 1ms for GSI {}: {res:?}", irq.gsi());
+        }
+        Err(e) => log::warn!("can't route GSI {COM1_GSI}: {e:?}"),
+    }
+
+    let cpu_time = sc_ctrl(ROOTTASK_SC_CAPSEL).expect("must query the SC of the roottask");
+    log::info!("roottask consumed {cpu_time:?} of CPU time so far");
+
+    ipc_demo(utcb);
+
+    for child in &children {
+        log::info!(
+            "child PD {}: EC {}, killed={}",
+            child.pd().sel(),
+            child.ec().sel(),
+            child.killed()
+        );
+    }
+
+    match msr_read(IA32_APIC_BASE_MSR) {
+        Ok(val) => log::info!("IA32_APIC_BASE = {val:#x}"),
+        Err(e) => log::warn!("can't read IA32_APIC_BASE: {e:?}"),
+    }
+
+    log::info!(
+        "stack: {} of {} bytes used so far",
+        stack::max_depth(),
+        stack::size()
+    );
+
+    // "reboot" on the command line of the roottask restarts the machine instead of spinning
+    if boot_modules::boot_modules(hip).take(1).any(|roottask| {
+        roottask
+            .cmdline
+            .split_whitespace()
+            .any(|arg| arg == "reboot")
+    }) {
+        power::reboot();
+    }
     panic!("game over")
 }
 
+/// Demonstration that IPC works. Creates a local EC with a portal that adds two numbers
+/// and calls it.
+fn ipc_demo(utcb: &mut Utcb) {
+    let stack_top = DEMO_EC_STACK.0.get() as u64 + size_of::<DemoStack>() as u64;
+    let utcb_addr = vspace::alloc(1, 0, vspace::VaUsage::Utcb).expect("must find room for a UTCB");
+    DEMO_EC_UTCB_ADDR.store(utcb_addr, Ordering::SeqCst);
+    let ec = Ec::create(
+        ROOTTASK_CAPSEL,
+        EcKind::Local,
+        0,
+        utcb_addr,
+        portal_stack_ptr(stack_top),
+        0,
+    )
+    .expect("local EC must be created");
+    let pt = Pt::create(
+        ROOTTASK_CAPSEL,
+        &ec,
+        Mtd::empty(),
+        demo_portal_entry as PortalEntry as usize as u64,
+    )
+    .expect("portal must be created");
+    pt.set_id(DEMO_PT_ID).expect("portal ID must be set");
+
+    utcb.set_message(&[20, 22], &[]).unwrap();
+    pt.call().expect("portal must be callable");
+    log::info!("IPC call: 20 + 22 = {:?}", utcb.untyped());
+}
+
+/// Entry of the portal of the IPC demonstration. Replies with the sum of all untyped items.
+extern "C" fn demo_portal_entry(id: PortalId) -> ! {
+    let utcb_addr = DEMO_EC_UTCB_ADDR.load(Ordering::SeqCst);
+    let utcb = unsafe { Utcb::from_ptr(utcb_addr as *mut u8) };
+    log::debug!("portal {id:#x} called with {:?}", utcb.untyped());
+    let sum = utcb.untyped().iter().sum::<u64>();
+    utcb.set_message(&[sum], &[]).unwrap();
+    ipc_reply()
+}
+
 // required by the Rust compiler.
+#[cfg(not(test))]
 #[panic_handler]
-fn panic_handler(info: &PanicInfo) -> ! {
+fn panic_handler(info: &core::panic::PanicInfo) -> ! {
+    use core::sync::atomic::{compiler_fence, Ordering};
     log::error!(
         "PANIC: {:?}",
         info.message().unwrap_or(&format_args!("<unknown>"))
     );
+    log::error!(
+        "stack: max depth {} of {} bytes",
+        stack::max_depth(),
+        stack::size()
+    );
     loop {
         compiler_fence(Ordering::SeqCst)
     }
@@ -0,0 +1,87 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Module for platform power transitions: reboot and ACPI suspend.
+
+use core::sync::atomic::{compiler_fence, Ordering};
+use libhedron::capability::CrdPortIO;
+use libhedron::error::HedronError;
+use libhedron::machine_ctrl::machine_ctrl_suspend;
+use libhedron::pd_ctrl::{pd_ctrl_delegate, DelegateFlags};
+use libhedron::ROOTTASK_CAPSEL;
+
+/// I/O port of the reset control register of the chipset.
+const RESET_CONTROL_PORT: u16 = 0xcf9;
+/// I/O port of the command register of the PS/2 keyboard controller.
+const KEYBOARD_CONTROLLER_PORT: u16 = 0x64;
+
+/// Resets the machine. Tries the reset control register of the chipset first and the
+/// PS/2 keyboard controller afterwards. Hedron has no syscall for this.
+pub fn reboot() -> ! {
+    log::info!("rebooting");
+    delegate_port_rights(RESET_CONTROL_PORT);
+    delegate_port_rights(KEYBOARD_CONTROLLER_PORT);
+    unsafe {
+        // "system reset" followed by "full reset" and "reset CPU"
+        outb(RESET_CONTROL_PORT, 0x02);
+        outb(RESET_CONTROL_PORT, 0x06);
+        // pulse the reset line of the CPU
+        outb(KEYBOARD_CONTROLLER_PORT, 0xfe);
+    }
+    loop {
+        compiler_fence(Ordering::SeqCst)
+    }
+}
+
+/// Suspends the machine into an ACPI sleep state. Returns after the machine resumed.
+/// See [`machine_ctrl_suspend`].
+#[allow(dead_code)]
+pub fn suspend(slp_typ_a: u8, slp_typ_b: u8) -> Result<(), HedronError> {
+    log::info!("suspending: SLP_TYPa={slp_typ_a:#x}, SLP_TYPb={slp_typ_b:#x}");
+    machine_ctrl_suspend(slp_typ_a, slp_typ_b)?;
+    log::info!("resumed");
+    Ok(())
+}
+
+/// Uses a PD_CTRL_DELEGATE syscall to delegate the rights for the I/O port into
+/// the I/O map of the roottask.
+fn delegate_port_rights(port: u16) {
+    let _ = pd_ctrl_delegate(
+        ROOTTASK_CAPSEL,
+        ROOTTASK_CAPSEL,
+        CrdPortIO::new(port, 0),
+        CrdPortIO::new(port, 0),
+        // most important boolean flag: "use hypervisor as src"
+        DelegateFlags::builder().hypervisor(true).build(),
+    );
+}
+
+/// Writes a byte to an I/O port.
+unsafe fn outb(port: u16, val: u8) {
+    core::arch::asm!(
+        "out dx, al",
+        in("dx") port,
+        in("al") val,
+        options(nomem, nostack, preserves_flags)
+    );
+}
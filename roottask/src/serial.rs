@@ -23,8 +23,11 @@ SOFTWARE.
 */
 //! Module that enables the usage of the serial device/the serial port/the COM1 port.
 
-use crate::{bda, pd_ctrl_delegate, CrdPortIO, DelegateFlags, ROOTTASK_CAPSEL};
+use crate::bda::BiosDataArea;
 use core::sync::atomic::{AtomicBool, Ordering};
+use libhedron::delegate::delegate_ports;
+use libhedron::pd_ctrl::DelegateFlags;
+use libhedron::ROOTTASK_CAPSEL;
 use uart_16550::SerialPort;
 
 /// Default port of the serial device / COM1 port.
@@ -34,9 +37,10 @@ const DEFAULT_COM1_PORT: u16 = 0x3f8;
 static PORT_DELEGATION_DONE: AtomicBool = AtomicBool::new(false);
 
 /// Returns a [SerialPort] object from [`uart_16550`]. In the background, the code finds the port of
-/// the serial device and maps itself all rights to access the corresponding I/O ports.
-pub fn get_serial_port() -> (SerialPort, u16) {
-    let port = find_serial_port();
+/// the serial device and maps itself all rights to access the corresponding I/O ports. Falls back
+/// to the default port, if no [`BiosDataArea`] is available.
+pub fn get_serial_port(bios_data_area: Option<&BiosDataArea>) -> (SerialPort, u16) {
+    let port = bios_data_area.map_or(DEFAULT_COM1_PORT, find_serial_port);
 
     if !PORT_DELEGATION_DONE.load(Ordering::SeqCst) {
         delegate_serial_port_rights(port);
@@ -50,23 +54,18 @@ pub fn get_serial_port() -> (SerialPort, u16) {
 /// Uses a PD_CTRL_DELEGATE syscall to delegate the rights for the corresponding I/O ports into
 /// the I/O map of the roottask.
 fn delegate_serial_port_rights(port: u16) {
-    let _ = pd_ctrl_delegate(
+    let _ = delegate_ports(
+        // Serial devices use 8 ports because they have 8 hardware registers
+        port..=port + 7,
         ROOTTASK_CAPSEL,
-        ROOTTASK_CAPSEL,
-        // order 3: means 2^3 == 8 => map 8 ports at once => optimization of NOVA/Hedron syscall interface
-        // Background: Serial devices us behind 8 ports because it has 8 hardware registers
-        CrdPortIO::new(port, 3),
-        CrdPortIO::new(port, 3),
         // most important boolean flag: "use hypervisor as src"
-        DelegateFlags::new(true, false, false, true, 0),
+        DelegateFlags::builder().hypervisor(true).build(),
     );
 }
 
 /// Finds the serial port from the BIOS data area. Uses the same mechanism as Hedron does
 /// internally. This doesn't work on modern UEFI boot flows by default.
-fn find_serial_port() -> u16 {
-    let bios_data_area = bda::get_bda();
-
+fn find_serial_port(bios_data_area: &BiosDataArea) -> u16 {
     if bios_data_area.com_1_port != 0 {
         bios_data_area.com_1_port
     } else if bios_data_area.com_2_port != 0 {
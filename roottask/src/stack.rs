@@ -0,0 +1,138 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Module for the initial stack of the roottask from `start.S`.
+//!
+//! `link.ld` places an unmapped guard page below the stack, so that a stack overflow causes
+//! a page fault. To find out how much of the stack is actually used, [`fill_canary`] fills
+//! the unused part of the stack with a pattern at boot. [`max_depth`] later reports how
+//! deep the stack was used by searching for the first overwritten word.
+
+use core::mem::size_of;
+use libhedron::PAGE_SIZE;
+
+/// Pattern for the unused part of the stack.
+const CANARY: u64 = 0xdead_beef_5ac4_c0de;
+/// Bytes below the current stack pointer that [`fill_canary`] leaves untouched. This is
+/// room for the stack frames of the fill loop itself, which are bigger in debug builds.
+const FILL_MARGIN: u64 = 0x1000;
+
+/// Returns the bounds of the stack as `(bottom, top)`.
+#[cfg(not(test))]
+pub fn bounds() -> (u64, u64) {
+    extern "C" {
+        static _initial_stack_bottom: u8;
+        static _initial_stack_top: u8;
+    }
+    // only the addresses of the symbols are of interest
+    unsafe {
+        (
+            &_initial_stack_bottom as *const u8 as u64,
+            &_initial_stack_top as *const u8 as u64,
+        )
+    }
+}
+
+/// The symbols of `start.S` don't exist in host-side unit tests.
+#[cfg(test)]
+pub fn bounds() -> (u64, u64) {
+    (0, 0)
+}
+
+/// Returns the size of the stack in bytes.
+pub fn size() -> u64 {
+    let (bottom, top) = bounds();
+    top - bottom
+}
+
+/// Returns true, if the address is inside the guard page below the stack.
+pub fn is_guard_page(addr: u64) -> bool {
+    let (bottom, _) = bounds();
+    (bottom.saturating_sub(PAGE_SIZE)..bottom).contains(&addr)
+}
+
+/// Fills the stack below the current stack pointer with [`CANARY`]. Call this once early
+/// during boot.
+pub fn fill_canary() {
+    let (bottom, _) = bounds();
+    let rsp: u64;
+    unsafe { core::arch::asm!("mov {}, rsp", out(reg) rsp) };
+    let end = (rsp - FILL_MARGIN) & !(size_of::<u64>() as u64 - 1);
+    if end > bottom {
+        let words = unsafe {
+            core::slice::from_raw_parts_mut(
+                bottom as *mut u64,
+                ((end - bottom) / size_of::<u64>() as u64) as usize,
+            )
+        };
+        fill(words);
+    }
+}
+
+/// Returns the maximum number of bytes of the stack that were used so far. Only meaningful
+/// after [`fill_canary`].
+pub fn max_depth() -> u64 {
+    let (bottom, top) = bounds();
+    let words = unsafe {
+        core::slice::from_raw_parts(
+            bottom as *const u64,
+            ((top - bottom) / size_of::<u64>() as u64) as usize,
+        )
+    };
+    depth(words)
+}
+
+fn fill(words: &mut [u64]) {
+    // volatile, because the compiler can't know that the memory is read later
+    for word in words {
+        unsafe { core::ptr::write_volatile(word, CANARY) };
+    }
+}
+
+/// Returns the number of bytes from the first word that is not [`CANARY`] to the end of
+/// the stack.
+fn depth(words: &[u64]) -> u64 {
+    let untouched = words
+        .iter()
+        .position(|word| unsafe { core::ptr::read_volatile(word) } != CANARY)
+        .unwrap_or(words.len());
+    ((words.len() - untouched) * size_of::<u64>()) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_depth() {
+        let mut stack = [0_u64; 64];
+        fill(&mut stack[..60]);
+        // the part above the stack pointer during the fill
+        assert_eq!(depth(&stack), 4 * 8);
+        // deeper use overwrites the canary
+        stack[40] = 0x1234;
+        assert_eq!(depth(&stack), 24 * 8);
+        fill(&mut stack);
+        assert_eq!(depth(&stack), 0);
+    }
+}
@@ -0,0 +1,408 @@
+/*
+MIT License
+
+Copyright (c) 2022 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+//! Module for the management of the virtual address space of the roottask. See [`VaSpace`].
+//!
+//! The manager knows all ranges that are in use from the beginning: the segments of the ELF
+//! file (from the symbols of `link.ld`), the stack and its guard page (from `start.S`), the
+//! UTCB, and the HIP.
+//! Everything else is handed out on demand by [`alloc`] or [`map`], so that drivers and
+//! loaders don't need hardcoded virtual addresses that might collide.
+
+use crate::sync::SpinLock;
+use libhedron::capability::{CrdMem, MemCapPermissions};
+use libhedron::delegate::{aligned_chunks, map_phys_range, RangeDelegationError};
+use libhedron::pd_ctrl::DelegateFlags;
+use libhedron::revoke::revoke;
+use libhedron::PAGE_SIZE;
+
+/// Maximum number of ranges that are in use at the same time.
+const MAX_REGIONS: usize = 256;
+/// Virtual memory below this address is never handed out. This keeps the null page and
+/// the area around the link address of the roottask free.
+const ALLOC_START: u64 = 0x1000_0000;
+/// End of the lower canonical half of the address space, i.e., the end of user space.
+const ALLOC_END: u64 = 0x8000_0000_0000;
+
+/// Global manager for the address space of the roottask. Initialized by [`init`].
+static VA_SPACE: SpinLock<VaSpace> = SpinLock::new(VaSpace::new());
+
+/// What a range of the virtual address space is used for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VaUsage {
+    /// The `.text` segment of the roottask.
+    Text,
+    /// The `.rodata` segment of the roottask.
+    Rodata,
+    /// The `.data` segment of the roottask, including `.bss`.
+    Data,
+    /// The initial stack from `start.S`.
+    #[cfg_attr(test, allow(dead_code))]
+    Stack,
+    /// The unmapped guard page below the stack.
+    #[cfg_attr(test, allow(dead_code))]
+    StackGuard,
+    /// A UTCB of an EC of the roottask.
+    Utcb,
+    /// The Hypervisor Information Page.
+    Hip,
+    /// Memory mapped by [`map`].
+    Mapping,
+    /// Address space that was reserved by [`alloc`].
+    Other,
+}
+
+/// Errors of [`map`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MapError {
+    /// The range to map is empty.
+    Empty,
+    /// There is no free virtual address range of the requested size.
+    OutOfVirtualMemory,
+    /// Hedron refused the mapping. Nothing remains mapped.
+    Delegation(RangeDelegationError),
+}
+
+/// Initializes the global address space manager with the ranges that are in use
+/// from the beginning. Must be called before anything else of this module.
+pub fn init(hip_addr: u64, utcb_addr: u64) {
+    let mut va_space = VA_SPACE.lock();
+    for (start, end, usage) in image_layout() {
+        va_space.reserve(start, end - start, usage);
+    }
+    va_space.reserve(utcb_addr, PAGE_SIZE, VaUsage::Utcb);
+    va_space.reserve(hip_addr, PAGE_SIZE, VaUsage::Hip);
+}
+
+/// Reserves `page_count` pages of free virtual address space for `usage`. The address is
+/// aligned to `2^align_order` pages.
+pub fn alloc(page_count: u64, align_order: u8, usage: VaUsage) -> Option<u64> {
+    VA_SPACE.lock().alloc(page_count, align_order, usage)
+}
+
+/// Gives address space back that was reserved by [`alloc`]. Nothing is unmapped.
+pub fn free(addr: u64) {
+    VA_SPACE.lock().free(addr)
+}
+
+/// Maps the physical range into a free range of the address space of the roottask and
+/// returns the virtual address of `phys`. `phys` and `len` don't need to be page-aligned;
+/// the mapping covers all touched pages. The virtual range is aligned like the physical one,
+/// so that Hedron can use large pages and few delegations are necessary.
+///
+/// `flags` must have the hypervisor flag set for physical memory that the roottask doesn't
+/// own yet, such as MMIO regions or firmware tables.
+pub fn map(
+    phys: u64,
+    len: u64,
+    permissions: MemCapPermissions,
+    flags: DelegateFlags,
+) -> Result<u64, MapError> {
+    if len == 0 {
+        return Err(MapError::Empty);
+    }
+    let page_offset = phys % PAGE_SIZE;
+    let phys_page = phys / PAGE_SIZE;
+    let page_count = (page_offset + len + PAGE_SIZE - 1) / PAGE_SIZE;
+    // the alignment of the physical range, but not larger than the range itself
+    let align_order = phys_page
+        .trailing_zeros()
+        .min(63 - page_count.leading_zeros()) as u8;
+
+    let virt =
+        alloc(page_count, align_order, VaUsage::Mapping).ok_or(MapError::OutOfVirtualMemory)?;
+    if let Err(e) = map_phys_range(
+        phys_page * PAGE_SIZE,
+        virt,
+        page_count * PAGE_SIZE,
+        permissions,
+        flags,
+    ) {
+        unmap_pages(virt / PAGE_SIZE, e.delegated);
+        free(virt);
+        return Err(MapError::Delegation(e));
+    }
+    Ok(virt + page_offset)
+}
+
+/// Removes a mapping that was created by [`map`] and gives the address space back.
+/// `addr` can be any address inside the mapping.
+pub fn unmap(addr: u64) {
+    let region = VA_SPACE
+        .lock()
+        .find(addr)
+        .filter(|r| r.usage == VaUsage::Mapping)
+        .unwrap_or_else(|| panic!("no mapping at {addr:#x}"));
+    unmap_pages(region.base, region.count);
+    free(region.base * PAGE_SIZE);
+}
+
+/// Returns the usage of the virtual address, if it is in use.
+#[allow(dead_code)]
+pub fn usage(addr: u64) -> Option<VaUsage> {
+    VA_SPACE.lock().find(addr).map(|r| r.usage)
+}
+
+/// Removes the memory capabilities of the pages from the roottask and from everyone who got
+/// them from the roottask.
+fn unmap_pages(page: u64, count: u64) {
+    for chunk in aligned_chunks(page, page, count) {
+        let _ = revoke(
+            CrdMem::new(chunk.src, chunk.order, MemCapPermissions::all()),
+            true,
+            None,
+        );
+    }
+}
+
+/// Returns the ranges of the ELF segments, the stack, and its guard page as
+/// `(start, end, usage)`.
+#[cfg(not(test))]
+fn image_layout() -> [(u64, u64, VaUsage); 5] {
+    extern "C" {
+        static __text_start: u8;
+        static __text_end: u8;
+        static __rodata_start: u8;
+        static __rodata_end: u8;
+        static __data_start: u8;
+        static __data_end: u8;
+    }
+    // only the addresses of the symbols are of interest
+    let addr = |symbol: &u8| symbol as *const u8 as u64;
+    let (stack_bottom, stack_top) = crate::stack::bounds();
+    unsafe {
+        [
+            (addr(&__text_start), addr(&__text_end), VaUsage::Text),
+            (addr(&__rodata_start), addr(&__rodata_end), VaUsage::Rodata),
+            (addr(&__data_start), addr(&__data_end), VaUsage::Data),
+            (stack_bottom - PAGE_SIZE, stack_bottom, VaUsage::StackGuard),
+            (stack_bottom, stack_top, VaUsage::Stack),
+        ]
+    }
+}
+
+/// The symbols of the linker script don't exist in host-side unit tests.
+#[cfg(test)]
+fn image_layout() -> [(u64, u64, VaUsage); 0] {
+    []
+}
+
+/// A range of the address space that is in use, described by page numbers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Region {
+    /// First page number.
+    base: u64,
+    /// Number of pages.
+    count: u64,
+    usage: VaUsage,
+}
+
+impl Region {
+    const EMPTY: Self = Self {
+        base: 0,
+        count: 0,
+        usage: VaUsage::Other,
+    };
+
+    fn end(self) -> u64 {
+        self.base + self.count
+    }
+}
+
+/// Manager for a virtual address space. Keeps a sorted list of the disjoint ranges that
+/// are in use. Free address space is found between them.
+pub struct VaSpace {
+    regions: [Region; MAX_REGIONS],
+    len: usize,
+}
+
+impl VaSpace {
+    /// Creates a new, empty address space.
+    pub const fn new() -> Self {
+        Self {
+            regions: [Region::EMPTY; MAX_REGIONS],
+            len: 0,
+        }
+    }
+
+    /// Marks the pages that overlap with the range as used. Empty ranges are ignored.
+    /// Panics if the range overlaps with a range that is already in use.
+    pub fn reserve(&mut self, addr: u64, size: u64, usage: VaUsage) {
+        if size == 0 {
+            return;
+        }
+        let base = addr / PAGE_SIZE;
+        let end = (addr + size + PAGE_SIZE - 1) / PAGE_SIZE;
+        let index = self.insert_position(base);
+        assert!(
+            (index == 0 || self.regions[index - 1].end() <= base)
+                && (index == self.len || end <= self.regions[index].base),
+            "virtual range {addr:#x}+{size:#x} is already in use"
+        );
+        self.insert(
+            index,
+            Region {
+                base,
+                count: end - base,
+                usage,
+            },
+        );
+    }
+
+    /// Finds `page_count` free pages that are aligned to `2^align_order` pages, marks them
+    /// as used, and returns the virtual address.
+    pub fn alloc(&mut self, page_count: u64, align_order: u8, usage: VaUsage) -> Option<u64> {
+        assert!(page_count > 0, "must allocate at least one page");
+        let align = 1_u64 << align_order;
+        let align_up = |page: u64| (page + align - 1) & !(align - 1);
+
+        let mut candidate = align_up(ALLOC_START / PAGE_SIZE);
+        let mut index = self.insert_position(candidate);
+        // a region that starts below the candidate may still cover it
+        if index > 0 && self.regions[index - 1].end() > candidate {
+            candidate = align_up(self.regions[index - 1].end());
+        }
+        while index < self.len && candidate + page_count > self.regions[index].base {
+            candidate = align_up(candidate.max(self.regions[index].end()));
+            index += 1;
+        }
+        if candidate + page_count > ALLOC_END / PAGE_SIZE {
+            return None;
+        }
+        self.insert(
+            index,
+            Region {
+                base: candidate,
+                count: page_count,
+                usage,
+            },
+        );
+        Some(candidate * PAGE_SIZE)
+    }
+
+    /// Marks the range that starts at `addr` as free.
+    pub fn free(&mut self, addr: u64) {
+        let page = addr / PAGE_SIZE;
+        let index = self.regions[..self.len]
+            .iter()
+            .position(|r| r.base == page)
+            .unwrap_or_else(|| panic!("no virtual range at {addr:#x}"));
+        self.regions.copy_within(index + 1..self.len, index);
+        self.len -= 1;
+    }
+
+    /// Returns the range that contains the address.
+    fn find(&self, addr: u64) -> Option<Region> {
+        let page = addr / PAGE_SIZE;
+        self.regions[..self.len]
+            .iter()
+            .find(|r| (r.base..r.end()).contains(&page))
+            .copied()
+    }
+
+    /// Returns the index of the first range that starts behind `page`.
+    fn insert_position(&self, page: u64) -> usize {
+        self.regions[..self.len]
+            .iter()
+            .position(|r| r.base > page)
+            .unwrap_or(self.len)
+    }
+
+    fn insert(&mut self, index: usize, region: Region) {
+        assert!(self.len < MAX_REGIONS, "too many virtual ranges");
+        self.regions.copy_within(index..self.len, index + 1);
+        self.regions[index] = region;
+        self.len += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::simulator::{self, SimulatorConfig};
+    use libhedron::ROOTTASK_CAPSEL;
+
+    #[test]
+    fn test_alloc_skips_used_ranges() {
+        let mut va_space = VaSpace::new();
+        va_space.reserve(ALLOC_START, 3 * PAGE_SIZE, VaUsage::Data);
+        va_space.reserve(ALLOC_START + 0x5000, PAGE_SIZE, VaUsage::Utcb);
+
+        // fits into the gap
+        let a = va_space.alloc(2, 0, VaUsage::Other).unwrap();
+        assert_eq!(a, ALLOC_START + 0x3000);
+        // doesn't fit into the gap or violates the alignment
+        let b = va_space.alloc(1, 3, VaUsage::Other).unwrap();
+        assert_eq!(b, ALLOC_START + 0x8000);
+        let c = va_space.alloc(2, 0, VaUsage::Other).unwrap();
+        assert_eq!(c, ALLOC_START + 0x6000);
+
+        va_space.free(a);
+        assert_eq!(va_space.find(a), None);
+        assert_eq!(va_space.find(c + 0x1fff).unwrap().usage, VaUsage::Other);
+        assert_eq!(va_space.alloc(2, 0, VaUsage::Other), Some(a));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_reserve_overlapping() {
+        let mut va_space = VaSpace::new();
+        va_space.reserve(0x400000, 0x3000, VaUsage::Text);
+        va_space.reserve(0x402fff, 1, VaUsage::Rodata);
+    }
+
+    #[test]
+    fn test_map_empty_range() {
+        let flags = DelegateFlags::builder().hypervisor(true).build();
+        assert_eq!(
+            map(0x1000, 0, MemCapPermissions::READ, flags),
+            Err(MapError::Empty)
+        );
+    }
+
+    #[test]
+    fn test_map_and_unmap() {
+        simulator::reset(SimulatorConfig::default());
+        let virt = map(
+            0x400,
+            0x100,
+            MemCapPermissions::READ,
+            DelegateFlags::builder().hypervisor(true).build(),
+        )
+        .unwrap();
+        assert_eq!(virt % PAGE_SIZE, 0x400);
+        assert_eq!(usage(virt), Some(VaUsage::Mapping));
+        simulator::with(|sim| {
+            assert_eq!(
+                sim.mem_permissions(ROOTTASK_CAPSEL, virt / PAGE_SIZE),
+                Some(MemCapPermissions::READ)
+            );
+        });
+
+        unmap(virt);
+        assert_eq!(usage(virt), None);
+        simulator::with(|sim| {
+            assert_eq!(sim.mem_permissions(ROOTTASK_CAPSEL, virt / PAGE_SIZE), None);
+        });
+    }
+}